@@ -2,4 +2,5 @@ from .functions import *  # noqa: F401, F403
 
 # register the namespace
 from . import expr  # noqa: F401
-from .units import QuantityDtype, UnitDType  # noqa: F401
+from ._internal import define_dimension, define_unit, load_definitions  # noqa: F401
+from .units import ListUnitDType, QuantityDtype, UnitDType  # noqa: F401
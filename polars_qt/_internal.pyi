@@ -1 +1,19 @@
+from typing import Sequence
+
 __version__: str
+
+def define_dimension(
+    name: str, definition: str | None = None, replace: bool = False
+) -> None: ...
+def define_unit(
+    name: str,
+    dimension: str | None = None,
+    factor: float = 1.0,
+    base_unit: str | None = None,
+    offset: float | None = None,
+    aliases: Sequence[str] = (),
+    replace: bool = False,
+) -> None: ...
+def load_definitions(
+    text: str, replace: bool = False, from_scratch: bool = False
+) -> None: ...
@@ -3,8 +3,9 @@
 from typing import Optional
 import polars_qt as pqt
 import polars as pl
+from fractions import Fraction
 from functools import partial
-from .units import UnitDType
+from .units import QtUnit, QtUnits, UnitDType
 
 
 @pl.api.register_expr_namespace("qt")
@@ -55,15 +56,8 @@ class QuantitySeries:
         # units = [(unit[0], (1, 1)  else unit) for unit in units]
         # default denominator to 1 if not provided
         # units = [(name, (power[0], 1) if len(power)==1 else power) for name, power in units]
-        unit_series = pl.Series(
-            [
-                [
-                    {"name": name, "power": {"numer": power[0], "denom": power[1]}}
-                    for name, power in units
-                ]
-            ],
-            dtype=UnitDType,
-        )
+        unit = QtUnits([QtUnit(name, Fraction(*power)) for name, power in units])
+        unit_series = pl.Series([str(unit)], dtype=UnitDType)
         return pl.struct(
             value=self._series,
             unit=pl.lit(unit_series),
@@ -2,6 +2,7 @@ from __future__ import annotations
 
 __all__ = [
     "noop",
+    "with_unit",
     "add",
     "abs",
     "sin",
@@ -20,6 +21,11 @@ __all__ = [
     "cum_max",
     "cum_min",
     "cum_prod",
+    "cum_sum",
+    "cum_count",
+    "diff",
+    "shift",
+    "pct_change",
     "dot",
     "sqrt",
     "tan",
@@ -34,10 +40,35 @@ __all__ = [
     "std",
     "var",
     "sum",
+    "product",
     "pow",
+    "convert",
+    "format_unit",
+    "lit",
+    "eq",
+    "ne",
+    "lt",
+    "le",
+    "gt",
+    "ge",
+    "is_between",
+    "is_close",
+    "floor",
+    "ceil",
+    "round",
+    "sign",
+    "clip",
+    "exp",
+    "expm1",
+    "log",
+    "log1p",
+    "log10",
+    "log2",
 ]
 
 
+import numbers
+from fractions import Fraction
 from pathlib import Path
 from typing import TYPE_CHECKING, Any
 
@@ -68,7 +99,21 @@ def noop(expr: IntoExprColumn) -> pl.Expr:
     return plugin_fn("noop", expr)
 
 
-def add(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+def with_unit(expr: IntoExprColumn, unit: str, typed: bool = False) -> pl.Expr:
+    """Attach a unit to a numeric expression, e.g. `with_unit("speed", "km/h")`
+
+    With `typed=True` the unit is stored in the dtype of the quantity, so that unit
+    errors (e.g. adding `m` and `s`) are raised as soon as the query is planned, by
+    `collect_schema()`, instead of when it is collected. Results are typed when all
+    the quantities they are computed from are.
+    """
+    return plugin_fn("with_unit", expr, kwargs={"unit": unit, "typed": typed})
+
+
+def add(
+    expr_a: IntoExprColumn | float, expr_b: IntoExprColumn | float
+) -> pl.Expr:
+    """A plain number (e.g. `2` or a numeric column) can only be added to a dimensionless quantity"""
     return plugin_fn("add", expr_a, expr_b)
 
 
@@ -105,11 +150,13 @@ def arctanh(expr: IntoExprColumn) -> pl.Expr:
 
 
 def arg_max(expr: IntoExprColumn) -> pl.Expr:
-    return plugin_fn("arg_max", expr)
+    """The index of the largest value, as a plain integer"""
+    return plugin_fn("arg_max", expr, is_elem=False)
 
 
 def arg_min(expr: IntoExprColumn) -> pl.Expr:
-    return plugin_fn("arg_min", expr)
+    """The index of the smallest value, as a plain integer"""
+    return plugin_fn("arg_min", expr, is_elem=False)
 
 
 def cbrt(expr: IntoExprColumn) -> pl.Expr:
@@ -137,11 +184,42 @@ def cum_min(expr: IntoExprColumn) -> pl.Expr:
 
 
 def cum_prod(expr: IntoExprColumn) -> pl.Expr:
+    """The k-th non-null row is in the unit to the power k (e.g. `m`, `m**2`, `m**3`), so unless the quantity is
+    dimensionless the rows have different dimensions and the result can't be used in further quantity expressions
+    """
     return plugin_fn("cum_prod", expr, is_elem=False)
 
 
+def cum_sum(expr: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("cum_sum", expr, is_elem=False)
+
+
+def cum_count(expr: IntoExprColumn) -> pl.Expr:
+    """The number of non-null rows so far, as plain integers without a unit"""
+    return plugin_fn("cum_count", expr, is_elem=False)
+
+
+def diff(expr: IntoExprColumn, n: int = 1) -> pl.Expr:
+    """Differences of offset units (e.g. `degC`) are in their delta unit (e.g. `delta_degC`)"""
+    return plugin_fn("diff", expr, is_elem=False, kwargs={"n": n})
+
+
+def shift(
+    expr: IntoExprColumn, n: int = 1, fill_value: IntoExprColumn | None = None
+) -> pl.Expr:
+    """Shift rows by `n`, `fill_value` is a quantity (e.g. `lit(0, "m")`) converted to the unit of `expr`"""
+    fill = [] if fill_value is None else [fill_value]
+    return plugin_fn("shift", expr, *fill, is_elem=False, kwargs={"n": n})
+
+
+def pct_change(expr: IntoExprColumn, n: int = 1) -> pl.Expr:
+    """The relative change between rows, as a dimensionless quantity"""
+    return plugin_fn("pct_change", expr, is_elem=False, kwargs={"n": n})
+
+
 def dot(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
-    return plugin_fn("dot", expr_a, expr_b)
+    """The dot product is in the product of the units (e.g. `N*m` for `N` and `m`)"""
+    return plugin_fn("dot", expr_a, expr_b, is_elem=False)
 
 
 def neg(expr: IntoExprColumn) -> pl.Expr:
@@ -160,15 +238,24 @@ def tanh(expr: IntoExprColumn) -> pl.Expr:
     return plugin_fn("tanh", expr)
 
 
-def sub(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+def sub(
+    expr_a: IntoExprColumn | float, expr_b: IntoExprColumn | float
+) -> pl.Expr:
+    """A plain number (e.g. `2` or a numeric column) can only be subtracted from a dimensionless quantity"""
     return plugin_fn("sub", expr_a, expr_b)
 
 
-def mul(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+def mul(
+    expr_a: IntoExprColumn | float, expr_b: IntoExprColumn | float
+) -> pl.Expr:
+    """Either operand can be a plain number (e.g. `2` or a numeric column), as a dimensionless quantity"""
     return plugin_fn("mul", expr_a, expr_b)
 
 
-def div(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+def div(
+    expr_a: IntoExprColumn | float, expr_b: IntoExprColumn | float
+) -> pl.Expr:
+    """Either operand can be a plain number (e.g. `2` or a numeric column), as a dimensionless quantity"""
     return plugin_fn("div", expr_a, expr_b)
 
 
@@ -193,6 +280,7 @@ def std(expr: IntoExprColumn) -> pl.Expr:
 
 
 def var(expr: IntoExprColumn) -> pl.Expr:
+    """The variance is in the square of the unit (e.g. `m**2` for `m`)"""
     return plugin_fn("var", expr, is_elem=False)
 
 
@@ -200,14 +288,173 @@ def sum(expr: IntoExprColumn) -> pl.Expr:
     return plugin_fn("sum", expr, is_elem=False)
 
 
-def pow(expr: IntoExprColumn, exp: int | float) -> pl.Expr:
-    if isinstance(exp, int):
-        return plugin_fn("pow_int", expr, kwargs={"exp": exp})
-    elif isinstance(exp, float):
-        return plugin_fn("pow_float", expr, kwargs={"exp": exp})
+def product(expr: IntoExprColumn) -> pl.Expr:
+    """The product is in the unit to the power of the number of non-null values"""
+    return plugin_fn("product", expr, is_elem=False)
+
+
+def pow(
+    expr: IntoExprColumn,
+    exp: int | float | Fraction | IntoExprColumn,
+    max_denom: int = 100,
+) -> pl.Expr:
+    """
+    Raise a quantity to a power.
+
+    A `Fraction` exponent is exact (e.g. `Fraction(1, 3)` for a cube root), while a float exponent is snapped to the
+    closest fraction with a denominator of at most `max_denom` (e.g. `0.1` to `1/10`). An expression exponent (e.g. a
+    column) is only allowed when `expr` is dimensionless, as the result can't have a different unit per row.
+    """
+    if isinstance(exp, bool):
+        raise TypeError("Exponent must be a number or an expression, got a bool")
+    # numbers.Integral and numbers.Real also cover numpy scalars (e.g. `np.int64`), which aren't `int`
+    if isinstance(exp, numbers.Integral):
+        return plugin_fn("pow_int", expr, kwargs={"exp": int(exp)})
+    elif isinstance(exp, Fraction):
+        return plugin_fn(
+            "pow_rat",
+            expr,
+            kwargs={"numer": exp.numerator, "denom": exp.denominator},
+        )
+    elif isinstance(exp, numbers.Real):
+        return plugin_fn(
+            "pow_float", expr, kwargs={"exp": float(exp), "max_denom": max_denom}
+        )
     else:
-        raise ValueError("Exponenet must be int or float for quantities")
+        return plugin_fn("pow_column", expr, exp)
 
 
 def convert(expr: IntoExprColumn, to: str) -> pl.Expr:
     return plugin_fn("convert", expr, kwargs={"to": to})
+
+
+def format_unit(expr: IntoExprColumn, format: str = "plain") -> pl.Expr:
+    """
+    The unit of a quantity as a string.
+
+    `format` is one of "plain" (`m/s^2`), "unicode" (`m·s⁻²`), "pint" (`meter / second ** 2`)
+    or "latex" (`\\si{\\metre\\per\\second\\squared}`)
+    """
+    return plugin_fn("format_unit", expr, kwargs={"format": format})
+
+
+def lit(value: Any, unit: str, typed: bool = False) -> pl.Expr:
+    """A quantity literal, e.g. `lit(10, "km")`, to compare or combine with quantity columns"""
+    return with_unit(pl.lit(value), unit, typed=typed)
+
+
+def eq(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("eq", expr_a, expr_b)
+
+
+def ne(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("ne", expr_a, expr_b)
+
+
+def lt(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("lt", expr_a, expr_b)
+
+
+def le(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("le", expr_a, expr_b)
+
+
+def gt(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("gt", expr_a, expr_b)
+
+
+def ge(expr_a: IntoExprColumn, expr_b: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("ge", expr_a, expr_b)
+
+
+def is_between(
+    expr: IntoExprColumn,
+    lower_bound: IntoExprColumn,
+    upper_bound: IntoExprColumn,
+    closed: str = "both",
+) -> pl.Expr:
+    """`closed` is one of "both", "left", "right" or "none", as in `pl.Expr.is_between`"""
+    return plugin_fn(
+        "is_between", expr, lower_bound, upper_bound, kwargs={"closed": closed}
+    )
+
+
+def is_close(
+    expr_a: IntoExprColumn,
+    expr_b: IntoExprColumn,
+    abs_tol: float = 0.0,
+    rel_tol: float = 1e-9,
+) -> pl.Expr:
+    """
+    Whether two quantities are close, with the same tolerances as `math.isclose`.
+
+    `abs_tol` is in the unit of `expr_a`
+    """
+    return plugin_fn(
+        "is_close", expr_a, expr_b, kwargs={"abs_tol": abs_tol, "rel_tol": rel_tol}
+    )
+
+
+def floor(expr: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("floor", expr)
+
+
+def ceil(expr: IntoExprColumn) -> pl.Expr:
+    return plugin_fn("ceil", expr)
+
+
+def round(expr: IntoExprColumn, decimals: int = 0) -> pl.Expr:
+    return plugin_fn("round", expr, kwargs={"decimals": decimals})
+
+
+def sign(expr: IntoExprColumn) -> pl.Expr:
+    """The sign of a quantity, as a dimensionless quantity"""
+    return plugin_fn("sign", expr)
+
+
+def clip(
+    expr: IntoExprColumn,
+    lower_bound: IntoExprColumn | None = None,
+    upper_bound: IntoExprColumn | None = None,
+) -> pl.Expr:
+    """Clip a quantity, the bounds are quantities (e.g. `lit(2, "km")`) converted to the unit of `expr`"""
+    bounds = [bound for bound in (lower_bound, upper_bound) if bound is not None]
+    return plugin_fn(
+        "clip",
+        expr,
+        *bounds,
+        kwargs={
+            "has_lower": lower_bound is not None,
+            "has_upper": upper_bound is not None,
+        },
+    )
+
+
+def exp(expr: IntoExprColumn) -> pl.Expr:
+    """Only for dimensionless quantities (e.g. `m/km`), the result is dimensionless"""
+    return plugin_fn("exp", expr)
+
+
+def expm1(expr: IntoExprColumn) -> pl.Expr:
+    """Only for dimensionless quantities (e.g. `m/km`), the result is dimensionless"""
+    return plugin_fn("expm1", expr)
+
+
+def log(expr: IntoExprColumn) -> pl.Expr:
+    """Only for dimensionless quantities (e.g. `m/km`), the result is dimensionless"""
+    return plugin_fn("log", expr)
+
+
+def log1p(expr: IntoExprColumn) -> pl.Expr:
+    """Only for dimensionless quantities (e.g. `m/km`), the result is dimensionless"""
+    return plugin_fn("log1p", expr)
+
+
+def log10(expr: IntoExprColumn) -> pl.Expr:
+    """Only for dimensionless quantities (e.g. `m/km`), the result is dimensionless"""
+    return plugin_fn("log10", expr)
+
+
+def log2(expr: IntoExprColumn) -> pl.Expr:
+    """Only for dimensionless quantities (e.g. `m/km`), the result is dimensionless"""
+    return plugin_fn("log2", expr)
@@ -8,20 +8,26 @@ import pint
 from fractions import Fraction
 
 
-__all__ = ["QuantityDtype", "UnitDType"]
+__all__ = ["QuantityDtype", "UnitDType", "ListUnitDType"]
 
-UnitDType = pl.List(
+# units are stored as their canonical string in every row (e.g. "kg*m/s^2")
+UnitDType = pl.String
+
+# the list layout of earlier versions, still accepted as input
+ListUnitDType = pl.List(
     pl.Struct(
         {"name": pl.Utf8, "power": pl.Struct({"numer": pl.Int64, "denom": pl.Int64})}
     )
 )
 
+DIMENSIONLESS = "dimensionless"
+
 
-def QuantityDtype(dtype: pl.DataType) -> pl.DataType:
+def QuantityDtype(dtype: pl.DataType, unit_dtype: pl.DataType = UnitDType) -> pl.DataType:
     return pl.Struct(
         {
             "value": dtype,
-            "unit": UnitDType,
+            "unit": unit_dtype,
         }
     )
 
@@ -45,12 +51,51 @@ class QtUnit:
 class QtUnits:
     units: list[QtUnit]
 
+    def __str__(self) -> str:
+        """Same format as the units stored by the plugin, e.g. `kg*m/s^2` or `s^(1/2)`"""
+
+        def join(units: list[QtUnit]) -> str:
+            return "*".join(unit.name + _format_power(unit.power) for unit in units)
+
+        numerator = [unit for unit in self.units if unit.power > 0]
+        denominator = [QtUnit(unit.name, -unit.power) for unit in self.units if unit.power < 0]
+        if not numerator and not denominator:
+            return DIMENSIONLESS
+        num = join(numerator) if numerator else "1"
+        if not denominator:
+            return num
+        if len(denominator) == 1:
+            return f"{num}/{join(denominator)}"
+        return f"{num}/({join(denominator)})"
+
     def as_lit(self) -> pl.Expr:
-        return pl.lit([unit.as_dict() for unit in self.units], dtype=UnitDType)
+        return pl.lit(str(self), dtype=UnitDType)
+
+    @classmethod
+    def from_str(cls, unit: str) -> "QtUnits":
+        """from the string of a unit stored by the plugin (see `__str__`)"""
+        if unit == DIMENSIONLESS:
+            return cls([])
+        # the `/` of the denominator is the only one outside of parentheses, e.g. `s^(1/2)/(kg*m)`
+        depth, num, den = 0, unit, ""
+        for idx, char in enumerate(unit):
+            depth += {"(": 1, ")": -1}.get(char, 0)
+            if char == "/" and depth == 0:
+                num, den = unit[:idx], unit[idx + 1 :]
+                break
+        if den.startswith("(") and den.endswith(")"):
+            den = den[1:-1]
+        units = [_parse_unit(part, 1) for part in num.split("*") if part != "1"]
+        if den:
+            units += [_parse_unit(part, -1) for part in den.split("*")]
+        return cls(units)
 
     @classmethod
     def from_quantity_scalar(cls, scalar: dict) -> "QtUnits":
         """from a polars Quantity scaler (i.e. a single row), ignores the value"""
+        if isinstance(scalar["unit"], str):
+            return cls.from_str(scalar["unit"])
+        # list layout
         return cls(
             [
                 QtUnit(
@@ -83,3 +128,16 @@ class QtUnits:
         pint_quantity = 1 * self.to_pint()
         converted = pint_quantity.to(to_unit)
         return QtUnits.from_pint(converted.units), converted.magnitude
+
+
+def _format_power(power: Fraction) -> str:
+    if power == 1:
+        return ""
+    if power.denominator == 1:
+        return f"^{power.numerator}"
+    return f"^({power.numerator}/{power.denominator})"
+
+
+def _parse_unit(unit: str, sign: int) -> QtUnit:
+    name, _, power = unit.partition("^")
+    return QtUnit(name, sign * Fraction(power.strip("()") or 1))
@@ -1,14 +1,17 @@
 #![allow(clippy::unused_unit)]
 
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::collections::HashMap;
+use std::slice;
 
+use num_rational::Rational64;
+use polars::chunked_array::ops::arity::broadcast_binary_elementwise;
 use polars::frame::column::ScalarColumn;
 use polars::prelude::*;
+use polars::series::ops::NullBehavior;
 use pyo3_polars::derive::polars_expr;
 use serde::Deserialize;
 
-use crate::expressions::polars_plan::prelude::Expr;
-use crate::units::definitions::REGISTRY;
+use crate::units::definitions::registry;
 use crate::units::*;
 
 #[allow(clippy::get_first)]
@@ -30,62 +33,439 @@ fn check_valid_quantity_dtype(dtype: &DataType) -> PolarsResult<bool> {
     }
 }
 
-fn is_all_same<T: PartialEq>(slice: &[T]) -> bool {
-    slice.windows(2).all(|w| w[0] == w[1])
-}
-
-fn quantity_output(input_fields: &[Field]) -> PolarsResult<Field> {
-    let dtypes: Vec<&DataType> = input_fields.iter().map(|f| f.dtype()).collect();
-    // simplying assumptions: the value dtype must be the same for all inputs
-    if !is_all_same(&dtypes) {
-        polars_bail!(InvalidOperation: "Expected all input fields to have the same dtype, got {:?}", dtypes)
-    }
-    // check that datatype is valid
-    check_valid_quantity_dtype(dtypes[0])?;
+/// Quantity struct with values of `value_dtype`. Its units are stored in the dtype (the typed layout, see
+/// `Units::typed_dtype`) when they are known when planning, otherwise in every row
+fn quantity_field(name: PlSmallStr, value_dtype: DataType, units: Option<Units>) -> PolarsResult<Field> {
+    let unit_dtype = match units {
+        Some(units) => units.typed_dtype()?,
+        None => Units::dtype(),
+    };
     Ok(Field::new(
-        "unit".into(),
+        name,
         DataType::Struct(vec![
-            Field::new("value".into(), dtypes[0].clone()),
-            Field::new("unit".into(), DataType::String),
+            Field::new("value".into(), value_dtype),
+            Field::new("unit".into(), unit_dtype),
         ]),
     ))
 }
 
-fn check_same_unit(ca: &ListChunked) -> PolarsResult<()> {
-    let mut iter = ca.iter();
-    let first = iter.next().unwrap();
-    if iter.all(|s| s == first) {
-        Ok(())
+/// Dtype of the values of a quantity field
+fn value_dtype(field: &Field) -> PolarsResult<DataType> {
+    check_valid_quantity_dtype(field.dtype())?;
+    match field.dtype() {
+        DataType::Struct(fields) => Ok(fields[0].dtype().clone()),
+        _ => unreachable!("checked to be a quantity"),
+    }
+}
+
+/// Dtype of the values of an operand, which is a quantity or a plain number (e.g. `2` in `speed * 2`)
+fn operand_dtype(field: &Field) -> PolarsResult<DataType> {
+    if field.dtype().is_numeric() {
+        Ok(field.dtype().clone())
+    } else {
+        value_dtype(field)
+    }
+}
+
+/// Units of a quantity field in the typed layout, `None` for quantities with their units in their rows
+fn typed_field_units(field: &Field) -> Option<PolarsResult<Units>> {
+    match field.dtype() {
+        DataType::Struct(fields) => fields
+            .get(1)
+            .and_then(|unit_field| Units::from_typed_dtype(unit_field.dtype())),
+        _ => None,
+    }
+}
+
+/// Units of all the inputs when they are known when planning, i.e. when all quantities are in the typed layout.
+/// Plain numbers are dimensionless
+fn planned_units(input_fields: &[Field]) -> PolarsResult<Option<Vec<Units>>> {
+    if input_fields.iter().all(|field| field.dtype().is_numeric()) {
+        return Ok(None);
+    }
+    let mut units = Vec::with_capacity(input_fields.len());
+    for field in input_fields {
+        if field.dtype().is_numeric() {
+            units.push(Units::dimensionless());
+        } else {
+            match typed_field_units(field) {
+                Some(field_units) => units.push(field_units?),
+                None => return Ok(None),
+            }
+        }
+    }
+    Ok(Some(units))
+}
+
+/// Output of an expression with values of `value_dtype`. When the units of the inputs are known, `unit_rule` computes
+/// the unit of the result, so that unit errors (e.g. adding `m` and `s`) are raised when the query is planned
+fn output_field(
+    input_fields: &[Field],
+    value_dtype: DataType,
+    unit_rule: impl FnOnce(&[Units]) -> PolarsResult<Units>,
+) -> PolarsResult<Field> {
+    let units = planned_units(input_fields)?
+        .map(|units| unit_rule(&units))
+        .transpose()?;
+    quantity_field(input_fields[0].name().clone(), value_dtype, units)
+}
+
+/// Dtype of the values returned by `kernel`, computed on empty values so that it is the dtype of the results whatever
+/// the rules of the kernel (e.g. `mean` of integers is Float64 and `sum` of Int16 is Int64)
+fn kernel_dtype(dtypes: &[DataType], kernel: impl FnOnce(&[Series]) -> PolarsResult<Series>) -> PolarsResult<DataType> {
+    let values: Vec<Series> = dtypes
+        .iter()
+        .map(|dtype| Series::new_empty("value".into(), dtype))
+        .collect();
+    Ok(kernel(&values)?.dtype().clone())
+}
+
+/// Output of a kernel on the values of a quantity
+fn unary_output(
+    input_fields: &[Field],
+    kernel: impl FnOnce(&Series) -> PolarsResult<Series>,
+    unit_rule: impl FnOnce(&[Units]) -> PolarsResult<Units>,
+) -> PolarsResult<Field> {
+    let dtype = kernel_dtype(&[value_dtype(&input_fields[0])?], |values| kernel(&values[0]))?;
+    output_field(input_fields, dtype, unit_rule)
+}
+
+/// Output of a kernel on the values of two operands, of which one can be a plain number. `conversions` tells which
+/// operands are converted to another unit before the kernel is applied
+fn binary_output(
+    input_fields: &[Field],
+    kernel: impl FnOnce(&Series, &Series) -> PolarsResult<Series>,
+    unit_rule: impl FnOnce(&[Units]) -> PolarsResult<Units>,
+    conversions: Conversions,
+) -> PolarsResult<Field> {
+    if input_fields[..2].iter().all(|field| field.dtype().is_numeric()) {
+        polars_bail!(InvalidOperation: "Expected at least one operand to be a quantity, got {:?}", input_fields)
+    }
+    let dtypes = converted_dtypes(&input_fields[..2], conversions)?;
+    let dtype = kernel_dtype(&dtypes, |values| kernel(&values[0], &values[1]))?;
+    output_field(input_fields, dtype, unit_rule)
+}
+
+/// Quantity with the values of the first input, cast to Float64 when integer values are combined with float ones
+/// (e.g. the bounds of `clip`, which are floats once converted to the unit of the first input when it is typed)
+fn with_float_operands_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    let dtypes = converted_dtypes(input_fields, bound_conversions)?;
+    let dtype = if !dtypes[0].is_float() && dtypes[1..].iter().any(|other| other.is_float()) {
+        DataType::Float64
+    } else {
+        dtypes[0].clone()
+    };
+    output_field(input_fields, dtype, keep_unit)
+}
+
+/// Which operands of an expression are converted to another unit, given their units when they are known when planning
+type Conversions = fn(&[Units]) -> Vec<bool>;
+
+/// Conversions of `add_quantities` and `sub_quantities`: differences combined with an offset unit are converted to
+/// its delta unit, other right operands to the unit of the left one
+fn additive_conversions(units: &[Units]) -> Vec<bool> {
+    // bound one at a time, so that the registry is not locked twice
+    let left_offset = registry().is_offset_unit(&units[0]);
+    let right_offset = registry().is_offset_unit(&units[1]);
+    match (left_offset, right_offset) {
+        (false, true) => vec![true, false],
+        (true, _) => vec![false, true],
+        (false, false) => vec![false, units[0] != units[1]],
+    }
+}
+
+/// Conversions of products: offset units are converted to their absolute unit, as in `to_absolute`
+fn absolute_conversions(units: &[Units]) -> Vec<bool> {
+    units.iter().map(|unit| registry().is_offset_unit(unit)).collect()
+}
+
+/// Conversions of the bounds of `clip` or the fill value of `shift` to the unit of the first input
+fn bound_conversions(units: &[Units]) -> Vec<bool> {
+    units.iter().map(|unit| *unit != units[0]).collect()
+}
+
+/// Dtypes of the values of operands once converted by `conversions`. When the units are known when planning, converted
+/// integer values are Float64 (see `float_operands`), otherwise values are converted in their dtype (see
+/// `convert_values`)
+fn converted_dtypes(input_fields: &[Field], conversions: Conversions) -> PolarsResult<Vec<DataType>> {
+    let converted = match planned_units(input_fields)? {
+        Some(units) => conversions(&units),
+        None => vec![false; input_fields.len()],
+    };
+    input_fields
+        .iter()
+        .zip(converted)
+        .map(|(field, converted)| {
+            let dtype = operand_dtype(field)?;
+            Ok(if converted { float_dtype(&dtype) } else { dtype })
+        })
+        .collect()
+}
+
+/// Quantity with the values and the unit of the first input unchanged, rows in other units (e.g. `cm` rows in a `m`
+/// column) are converted in the dtype of the values (see `convert_values`)
+fn quantity_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    output_field(input_fields, value_dtype(&input_fields[0])?, keep_unit)
+}
+
+/// Float dtype of converted values, Float32 values stay Float32 and others are Float64
+fn float_dtype(dtype: &DataType) -> DataType {
+    match dtype {
+        DataType::Float32 => DataType::Float32,
+        _ => DataType::Float64,
+    }
+}
+
+/// Quantity with float values, for expressions that don't keep integer values
+fn float_output(
+    input_fields: &[Field],
+    unit_rule: impl FnOnce(&[Units]) -> PolarsResult<Units>,
+) -> PolarsResult<Field> {
+    output_field(input_fields, float_dtype(&value_dtype(&input_fields[0])?), unit_rule)
+}
+
+/// Unit rule of expressions keeping the unit of the first input, the units of other inputs (e.g. the bounds of `clip`)
+/// must be convertible to it
+fn keep_unit(units: &[Units]) -> PolarsResult<Units> {
+    for other in &units[1..] {
+        check_convertible(other, &units[0])?;
+    }
+    Ok(units[0].clone())
+}
+
+fn check_convertible(from: &Units, to: &Units) -> PolarsResult<()> {
+    // bound first, so that the registry is not locked while formatting the error
+    let conversion = registry().convert_between(from, to);
+    conversion.map(|_| ()).map_err(registry_error)
+}
+
+/// Unit rule of expressions converting their input to `in_unit` (e.g. `radian` for `sin`) and returning `out_unit`
+fn in_unit_rule(units: &[Units], expected: &str, in_unit: &Units, out_unit: &Units) -> PolarsResult<Units> {
+    check_convertible(&units[0], in_unit)
+        .map_err(|error| polars_err!(InvalidOperation: "Expected {}, got {}: {}", expected, units[0], error))?;
+    Ok(out_unit.clone())
+}
+
+/// Whether a quantity is in the typed layout, with its unit in its dtype
+fn is_typed(s: &Series) -> bool {
+    match s.dtype() {
+        DataType::Struct(fields) => fields
+            .get(1)
+            .is_some_and(|unit_field| Units::from_typed_dtype(unit_field.dtype()).is_some()),
+        _ => false,
+    }
+}
+
+/// Whether all quantities are in the typed layout, as `planned_units` when planning
+fn all_typed(inputs: &[Series]) -> bool {
+    let mut quantities = inputs.iter().filter(|s| !s.dtype().is_numeric()).peekable();
+    quantities.peek().is_some() && quantities.all(is_typed)
+}
+
+/// Store the unit of a quantity in the typed layout or in every row
+fn with_layout(s: Series, typed: bool) -> PolarsResult<Series> {
+    // the list layout of inputs is not kept, results are in the typed or in the default layout
+    let in_layout = match s.dtype() {
+        DataType::Struct(fields) if !typed => fields.get(1).is_some_and(|unit| unit.dtype() == &Units::dtype()),
+        _ => is_typed(&s) == typed,
+    };
+    if in_layout {
+        return Ok(s);
+    }
+    let fields = s.struct_()?.fields_as_series();
+    let (value, units) = (fields[0].clone(), Units::from_scalar(fields[1].first())?);
+    let len = value.len();
+    let unit_col = if typed {
+        UInt32Chunked::full("unit".into(), 0, len)
+            .into_series()
+            .cast(&units.typed_dtype()?)?
+    } else {
+        ScalarColumn::new("unit".into(), units.to_scalar()?, len).take_materialized_series()
+    };
+    Ok(StructChunked::from_series(s.name().clone(), len, [value, unit_col].iter())?.into_series())
+}
+
+/// Store the unit of a result in the layout declared when planning: typed when the units of all inputs are
+fn to_input_layout(result: Series, inputs: &[Series]) -> PolarsResult<Series> {
+    with_layout(result, all_typed(inputs))
+}
+
+/// Operands with float values when they are converted to another unit (see `Conversions`) and their units are known
+/// when planning, as declared by `converted_dtypes`. Operands of other quantities are converted in their dtype
+fn float_operands(inputs: &[Series], conversions: Conversions) -> PolarsResult<Vec<Series>> {
+    if !all_typed(inputs) {
+        return Ok(inputs.to_vec());
+    }
+    let units = inputs
+        .iter()
+        .map(|s| {
+            if s.dtype().is_numeric() {
+                Ok(Units::dimensionless())
+            } else {
+                first_unit(s)
+            }
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
+    inputs
+        .iter()
+        .zip(conversions(&units))
+        .map(|(s, converted)| if converted { with_float_values(s) } else { Ok(s.clone()) })
+        .collect()
+}
+
+/// Cast integer values of a quantity or a plain number to Float64, e.g. to convert them to another unit
+fn with_float_values(s: &Series) -> PolarsResult<Series> {
+    if s.dtype().is_numeric() {
+        return s.cast(&float_dtype(s.dtype()));
+    }
+    check_valid_quantity_dtype(s.dtype())?;
+    let fields = s.struct_()?.fields_as_series();
+    let value = fields[0].cast(&float_dtype(fields[0].dtype()))?;
+    Ok(StructChunked::from_series(s.name().clone(), s.len(), [value, fields[1].clone()].iter())?.into_series())
+}
+
+/// Convert the values of rows with a different unit than the first row (e.g. `g` and `lb` rows in a `kg` column)
+/// to the unit of the first row, so that expressions only need to look at the unit of the first row
+fn to_first_unit(value: Series, unit: &Series) -> PolarsResult<Series> {
+    let dtype = unit.dtype();
+    if let DataType::String = dtype {
+        let to_units = |row: Option<&str>| match row {
+            Some(row) => row.parse(),
+            None => polars_bail!(ComputeError: "Unit is Null"),
+        };
+        rows_to_first_unit(value, unit.str()?.iter(), to_units)
+    } else {
+        let to_units = |row: Option<Series>| {
+            Units::from_scalar(Scalar::new(dtype.clone(), row.map_or(AnyValue::Null, AnyValue::List)))
+        };
+        rows_to_first_unit(value, unit.list()?.into_iter(), to_units)
+    }
+}
+
+/// `to_first_unit` on the stored units of the rows (e.g. the strings of the default layout)
+fn rows_to_first_unit<T: PartialEq + Clone>(
+    value: Series,
+    mut rows: impl Iterator<Item = T>,
+    to_units: impl Fn(T) -> PolarsResult<Units>,
+) -> PolarsResult<Series> {
+    let Some(first) = rows.next() else {
+        return Ok(value);
+    };
+    let len = value.len();
+    let is_float = value.dtype().is_float();
+    let mut first_units = None;
+    // per row (factor, offset), only allocated if a row needs to be converted
+    let mut conversions: Option<(Vec<f64>, Vec<f64>)> = None;
+    let mut cache: HashMap<Units, (f64, f64)> = HashMap::new();
+    for (idx, row) in rows.enumerate() {
+        // cheap check on the stored representation first, then compare semantically (e.g. `m*s` and `s*m`)
+        if row == first {
+            continue;
+        }
+        if first_units.is_none() {
+            first_units = Some(to_units(first.clone())?);
+        }
+        let (first_units, row_units) = (first_units.as_ref().unwrap(), to_units(row)?);
+        if *first_units == row_units {
+            continue;
+        }
+        let (factor, offset) = match cache.get(&row_units) {
+            Some(conversion) => *conversion,
+            None => {
+                let conversion = registry().convert_between(&row_units, first_units).map_err(
+                    |error| polars_err!(InvalidOperation: "Expected all units to be compatible: {:#}", error),
+                )?;
+                let conversion = if is_float {
+                    conversion
+                } else {
+                    integer_conversion(conversion).ok_or_else(|| integer_conversion_error(&row_units, first_units))?
+                };
+                cache.insert(row_units, conversion);
+                conversion
+            },
+        };
+        let (factors, offsets) = conversions.get_or_insert_with(|| (vec![1.0; len], vec![0.0; len]));
+        factors[idx + 1] = factor;
+        offsets[idx + 1] = offset;
+    }
+    match conversions {
+        None => Ok(value),
+        Some((factors, offsets)) => convert_values(&value, &factors, &offsets),
+    }
+}
+
+/// Convert values by `value * factor + offset` in their dtype, so that converted values have the dtype declared when
+/// planning (e.g. Float32 values stay Float32). `factors` and `offsets` have a single row or a row per value, which
+/// are integers for integer values (see `integer_conversion`)
+fn convert_values(value: &Series, factors: &[f64], offsets: &[f64]) -> PolarsResult<Series> {
+    let dtype = value.dtype();
+    let in_dtype = |name: &str, numbers: &[f64]| Series::new(name.into(), numbers).strict_cast(dtype);
+    let converted = (value * &in_dtype("factor", factors)?)?;
+    if offsets.iter().all(|offset| *offset == 0.0) {
+        Ok(converted)
     } else {
-        polars_bail!(InvalidOperation: "Expected all units to be the same")
+        &converted + &in_dtype("offset", offsets)?
     }
 }
 
+/// Conversion of integer values, which stay integers: only integer factors and offsets (e.g. from `km` to `m`) don't
+/// truncate them. Rounding errors of the factors of the registry are rounded off
+fn integer_conversion((factor, offset): (f64, f64)) -> Option<(f64, f64)> {
+    let to_integer = |number: f64| {
+        let rounded = number.round();
+        ((number - rounded).abs() <= 1e-9 * number.abs()).then_some(rounded)
+    };
+    Some((to_integer(factor)?, to_integer(offset)?))
+}
+
+/// Error of converting integer values by a factor or an offset which isn't an integer (e.g. from `m` to `km`)
+fn integer_conversion_error(from: &Units, to: &Units) -> PolarsError {
+    polars_err!(InvalidOperation: "Cannot convert integer values from {} to {} without truncating them, cast them to floats first", from, to)
+}
+
 #[allow(clippy::get_first)]
 fn extract_quantity(input: &Series) -> PolarsResult<(Series, Series)> {
     let ca = input.struct_()?;
     check_valid_quantity_dtype(ca.dtype())?;
     let fields = &ca.fields_as_series();
     let (value, unit) = (fields.get(0).unwrap().clone(), fields.get(1).unwrap().clone());
-    check_same_unit(unit.list()?)?;
+    // rows of the typed layout share the unit of the dtype
+    let value = if is_typed(input) {
+        value
+    } else {
+        to_first_unit(value, &unit)?
+    };
 
     Ok((value, unit))
 }
 
 fn add_unit(series: Series, unit_val: Scalar) -> PolarsResult<Series> {
-    println!("add_unit: unit_val: {:?}", unit_val);
     let unit_col = ScalarColumn::new("unit".into(), unit_val, series.len());
     let (name, len) = (series.name().clone(), series.len());
     let fields = [series, unit_col.take_materialized_series()];
     Ok(StructChunked::from_series(name, len, fields.iter())?.into_series())
 }
 
-fn extract_result(df: DataFrame) -> Series {
-    let idx = df.get_column_index("result").unwrap();
-    df.take_columns()
-        .remove(idx)
-        .with_name("value".into())
-        .take_materialized_series()
+/// Attach a unit per row, e.g. the powers of a unit for a cumulative product
+fn add_unit_per_row(series: Series, units: Vec<Units>) -> PolarsResult<Series> {
+    let mut rows: Vec<PlSmallStr> = Vec::with_capacity(units.len());
+    // consecutive rows usually have the same unit, format it only once
+    let mut last: Option<(Units, PlSmallStr)> = None;
+    for unit in units {
+        let row = match &last {
+            Some((last_unit, row)) if *last_unit == unit => row.clone(),
+            _ => {
+                let row: PlSmallStr = unit.canonical().to_string().into();
+                last = Some((unit, row.clone()));
+                row
+            },
+        };
+        rows.push(row);
+    }
+    let unit_col = StringChunked::from_iter_values("unit".into(), rows.iter().map(|row| row.as_str())).into_series();
+    let (name, len) = (series.name().clone(), series.len());
+    let fields = [series, unit_col];
+    Ok(StructChunked::from_series(name, len, fields.iter())?.into_series())
 }
 
 fn get_new_unit(
@@ -93,22 +473,28 @@ fn get_new_unit(
     unit_right: Scalar,
     unit_tfms: Option<fn(Units, Units) -> Units>,
 ) -> PolarsResult<Scalar> {
-    Ok(if let Some(tfms) = unit_tfms {
-        tfms(Units::from_scalar(unit_left)?, Units::from_scalar(unit_right)?).to_scalar()?
-    } else if unit_left == unit_right {
-        polars_bail!(InvalidOperation: "Expected units to be the same, got {:?} and {:?}", unit_left, unit_right)
+    let (units_left, units_right) = (Units::from_scalar(unit_left.clone())?, Units::from_scalar(unit_right)?);
+    if let Some(tfms) = unit_tfms {
+        tfms(units_left, units_right).to_scalar()
+    } else if units_left != units_right {
+        polars_bail!(InvalidOperation: "Expected units to be the same, got {} and {}", units_left, units_right)
     } else {
-        unit_left
-    })
+        // the unit is kept in its layout
+        Ok(unit_left)
+    }
 }
 
-fn apply_unary<F>(input: &Series, expr: Expr, unit_tfms: Option<F>) -> PolarsResult<Series>
+/// Apply a kernel (e.g. `abs` or `mean`) to the values of a quantity
+fn apply_unary<F>(
+    input: &Series,
+    kernel: impl FnOnce(&Series) -> PolarsResult<Series>,
+    unit_tfms: Option<F>,
+) -> PolarsResult<Series>
 where
     F: Fn(Units) -> Units,
 {
     let (value, unit) = extract_quantity(input)?;
-    let df = df!["value" => value]?.lazy().select(&[expr]).collect()?;
-    let result = extract_result(df);
+    let result = kernel(&value)?.with_name("value".into());
     let new_unit = if let Some(tfms) = unit_tfms {
         tfms(Units::from_scalar(unit.first())?).to_scalar()?
     } else {
@@ -117,60 +503,147 @@ where
     add_unit(result, new_unit)
 }
 
+/// Apply a kernel (e.g. addition) to the values of two quantities
 fn apply_binary(
     left: &Series,
     right: &Series,
-    expr: Expr,
+    kernel: impl FnOnce(&Series, &Series) -> PolarsResult<Series>,
     unit_tfms: Option<fn(Units, Units) -> Units>, // TODO: the absence of this should imply that the units should be the same, not sure this is a good API to use an Option for it
 ) -> PolarsResult<Series> {
     let (value_left, unit_left) = extract_quantity(left)?;
     let (value_right, unit_right) = extract_quantity(right)?;
     let new_unit = get_new_unit(unit_left.first(), unit_right.first(), unit_tfms)?;
-    let df: DataFrame = df!["value_left" => value_left, "value_right" => value_right]?
-        .lazy()
-        .select(&[expr])
-        .collect()?;
-    let result = extract_result(df);
+    let result = kernel(&value_left, &value_right)?.with_name("value".into());
     add_unit(result, new_unit)
 }
 
-macro_rules! create_unit_unary_expr {
-    ($name:ident $(, $arg:expr)*) => {
-        #[polars_expr(output_type_func=quantity_output)]
-        fn $name(inputs: &[Series]) -> PolarsResult<Series> {
-            apply_unary::<fn(Units) -> Units>(&inputs[0], col("value").$name($($arg),*).alias("result"), None)
+/// Apply a function of floats to values, Float32 values stay Float32 and others are converted to Float64
+fn float_kernel(s: &Series, f: impl Fn(f64) -> f64 + Copy) -> PolarsResult<Series> {
+    match s.dtype() {
+        DataType::Float32 => Ok(s.f32()?.apply_values(|v| f(v as f64) as f32).into_series()),
+        _ => Ok(s.cast(&DataType::Float64)?.f64()?.apply_values(f).into_series()),
+    }
+}
+
+/// Aggregations (e.g. `mean`) return a single row
+fn reduce(s: &Series, f: impl FnOnce(&Series) -> PolarsResult<Scalar>) -> PolarsResult<Series> {
+    Ok(f(s)?.into_series(s.name().clone()))
+}
+
+/// Integer power, integer values stay integers as in polars
+fn pow_int_kernel(s: &Series, exp: i64) -> PolarsResult<Series> {
+    if s.dtype().is_float() {
+        // `powi` takes an i32, larger exponents (as the power of the unit) are not truncated
+        return match i32::try_from(exp) {
+            Ok(exp) => float_kernel(s, |v| v.powi(exp)),
+            Err(_) => float_kernel(s, |v| v.powf(exp as f64)),
+        };
+    }
+    if exp < 0 {
+        polars_bail!(InvalidOperation: "Cannot raise integer values to a negative power ({}), convert them to floats first", exp)
+    }
+    // x^0 is 1 (nulls stay nulls), then exponentiation by squaring with products of integer values
+    let ones = s.cast(&DataType::Float64)?.f64()?.apply_values(|_| 1.0);
+    let (mut result, mut base, mut exp) = (ones.into_series().cast(s.dtype())?, s.clone(), exp);
+    while exp > 0 {
+        if exp % 2 == 1 {
+            result = (&result * &base)?;
         }
-    };
+        exp /= 2;
+        if exp > 0 {
+            base = (&base * &base)?;
+        }
+    }
+    Ok(result)
 }
 
-macro_rules! create_unit_binary_expr {
-    ($name:ident) => {
-        #[polars_expr(output_type_func=quantity_output)]
-        fn $name(inputs: &[Series]) -> PolarsResult<Series> {
-            apply_binary(
-                &inputs[0],
-                &inputs[1],
-                col("value_left").$name(col("value_right")).alias("result"),
-                None,
-            )
+/// Sign of values as -1, 0 or 1 (and NaN for NaN) in the dtype of the values
+fn sign_kernel(s: &Series) -> PolarsResult<Series> {
+    let sign = s.cast(&DataType::Float64)?.f64()?.apply_values(|v| {
+        if v > 0.0 {
+            1.0
+        } else if v < 0.0 {
+            -1.0
+        } else {
+            v
         }
+    });
+    sign.into_series().cast(s.dtype())
+}
+
+/// Shift values by `n` rows, filling with the first value of `fill` or with nulls
+fn shift_kernel(s: &Series, n: i64, fill: Option<&Series>) -> PolarsResult<Series> {
+    let Some(fill) = fill else {
+        return Ok(s.shift(n));
     };
-    ($name:ident, $tfms_unit:expr) => {
-        #[polars_expr(output_type_func=quantity_output)]
+    let len = s.len();
+    let filled = (n.unsigned_abs() as usize).min(len);
+    let mut filler = if fill.is_empty() {
+        Series::full_null(s.name().clone(), filled, s.dtype())
+    } else {
+        fill.cast(s.dtype())?.new_from_index(0, filled)
+    };
+    if n >= 0 {
+        filler.append(&s.slice(0, len - filled))?;
+        Ok(filler.with_name(s.name().clone()))
+    } else {
+        let mut shifted = s.slice(filled as i64, len - filled);
+        shifted.append(&filler)?;
+        Ok(shifted)
+    }
+}
+
+/// Row index (e.g. of the largest value) as a single row, null when all values are null
+fn index_kernel(s: &Series, index: Option<usize>) -> Series {
+    IdxCa::from_slice_options(s.name().clone(), &[index.map(|index| index as IdxSize)]).into_series()
+}
+
+macro_rules! create_unit_unary_expr {
+    ($name:ident, $output:ident, $kernel:expr) => {
+        fn $output(input_fields: &[Field]) -> PolarsResult<Field> {
+            unary_output(input_fields, $kernel, keep_unit)
+        }
+
+        #[polars_expr(output_type_func=$output)]
         fn $name(inputs: &[Series]) -> PolarsResult<Series> {
-            apply_binary(
-                &inputs[0],
-                &inputs[1],
-                col("value_left").$name(col("value_right")).alias("result"),
-                Some($tfms_unit),
-            )
+            let result = apply_unary::<fn(Units) -> Units>(&inputs[0], $kernel, None)?;
+            to_input_layout(result, inputs)
         }
     };
 }
 
 #[polars_expr(output_type_func=quantity_output)]
 fn noop(inputs: &[Series]) -> PolarsResult<Series> {
-    apply_unary::<fn(Units) -> Units>(&inputs[0], col("value").alias("result"), None)
+    let result = apply_unary::<fn(Units) -> Units>(&inputs[0], |s| Ok(s.clone()), None)?;
+    to_input_layout(result, inputs)
+}
+
+#[derive(Deserialize)]
+struct WithUnitKwarg {
+    unit: String,
+    typed: bool,
+}
+
+fn with_unit_output(input_fields: &[Field], kwargs: WithUnitKwarg) -> PolarsResult<Field> {
+    let dtype = input_fields[0].dtype();
+    if !dtype.is_numeric() {
+        polars_bail!(InvalidOperation: "Unit supports only numeric types, got {}", dtype)
+    }
+    let units = if kwargs.typed { Some(kwargs.unit.parse()?) } else { None };
+    quantity_field(input_fields[0].name().clone(), dtype.clone(), units)
+}
+
+/// Attach a unit, given as a unit expression (e.g. `kg*m/s^2`), to a numeric column. With `typed`, the unit is stored
+/// in the dtype, so that the units of expressions on the quantity are checked when the query is planned
+#[polars_expr(output_type_func_with_kwargs=with_unit_output)]
+fn with_unit(inputs: &[Series], kwargs: WithUnitKwarg) -> PolarsResult<Series> {
+    let value = &inputs[0];
+    if !value.dtype().is_numeric() {
+        polars_bail!(InvalidOperation: "Unit supports only numeric types, got {}", value.dtype())
+    }
+    let unit: Units = kwargs.unit.parse()?;
+    let quantity = add_unit(value.clone().with_name("value".into()), unit.to_scalar()?)?;
+    with_layout(quantity, kwargs.typed)
 }
 
 #[derive(Deserialize)]
@@ -178,170 +651,2186 @@ struct PowIntKwarg {
     exp: i64,
 }
 
-#[polars_expr(output_type_func=quantity_output)]
+fn pow_int_output(input_fields: &[Field], kwargs: PowIntKwarg) -> PolarsResult<Field> {
+    unary_output(
+        input_fields,
+        |s| pow_int_kernel(s, kwargs.exp),
+        |units| Ok(units[0].pow_int(kwargs.exp)),
+    )
+}
+
+#[polars_expr(output_type_func_with_kwargs=pow_int_output)]
 fn pow_int(inputs: &[Series], kwargs: PowIntKwarg) -> PolarsResult<Series> {
-    apply_unary(
+    let result = apply_unary(
         &inputs[0],
-        col("value").pow(kwargs.exp).alias("result"),
+        |s| pow_int_kernel(s, kwargs.exp),
         Some(|u: Units| u.pow_int(kwargs.exp)),
+    )?;
+    to_input_layout(result, inputs)
+}
+
+#[derive(Deserialize)]
+struct PowRatKwarg {
+    numer: i64,
+    denom: i64,
+}
+
+fn pow_rat_output(input_fields: &[Field], kwargs: PowRatKwarg) -> PolarsResult<Field> {
+    let exp = rat_exponent(kwargs.numer, kwargs.denom)?;
+    pow_rat_field(input_fields, exp)
+}
+
+/// Output of a rational power, of which the values are floats whatever the exponent
+fn pow_rat_field(input_fields: &[Field], exp: Rational64) -> PolarsResult<Field> {
+    let exp_float = *exp.numer() as f64 / *exp.denom() as f64;
+    unary_output(
+        input_fields,
+        |s| float_kernel(s, |v| v.powf(exp_float)),
+        |units| Ok(units[0].pow_rat(exp)),
+    )
+}
+
+fn rat_exponent(numer: i64, denom: i64) -> PolarsResult<Rational64> {
+    if denom == 0 {
+        polars_bail!(InvalidOperation: "The denominator of an exponent cannot be zero")
+    }
+    Ok(Rational64::new(numer, denom))
+}
+
+/// Raise a quantity to an exact rational power, e.g. `1/3` for a cube root
+fn pow_rat_quantity(input: &Series, exp: Rational64) -> PolarsResult<Series> {
+    let exp_float = *exp.numer() as f64 / *exp.denom() as f64;
+    apply_unary(
+        input,
+        |s| float_kernel(s, |v| v.powf(exp_float)),
+        Some(|u: Units| u.pow_rat(exp)),
     )
 }
 
+#[polars_expr(output_type_func_with_kwargs=pow_rat_output)]
+fn pow_rat(inputs: &[Series], kwargs: PowRatKwarg) -> PolarsResult<Series> {
+    let result = pow_rat_quantity(&inputs[0], rat_exponent(kwargs.numer, kwargs.denom)?)?;
+    to_input_layout(result, inputs)
+}
+
 #[derive(Deserialize)]
 struct PowFloatKwarg {
     exp: f64,
+    max_denom: i64,
 }
 
-#[polars_expr(output_type_func=quantity_output)]
+/// Float exponents are snapped to the closest rational with a denominator of at most `max_denom` (e.g. `0.1` to
+/// `1/10`), which is used for both the values and the unit
+fn float_exponent(exp: f64, max_denom: i64) -> PolarsResult<Rational64> {
+    let Some(rational) = rational_from_f64(exp, max_denom) else {
+        polars_bail!(InvalidOperation: "Exponent {} cannot be represented as a rational power of a unit", exp)
+    };
+    Ok(rational)
+}
+
+fn pow_float_output(input_fields: &[Field], kwargs: PowFloatKwarg) -> PolarsResult<Field> {
+    pow_rat_field(input_fields, float_exponent(kwargs.exp, kwargs.max_denom)?)
+}
+
+#[polars_expr(output_type_func_with_kwargs=pow_float_output)]
 fn pow_float(inputs: &[Series], kwargs: PowFloatKwarg) -> PolarsResult<Series> {
-    apply_unary(
-        &inputs[0],
-        col("value").pow(kwargs.exp).alias("result"),
-        Some(|u: Units| u.pow_float(kwargs.exp)),
-    )
+    let result = pow_rat_quantity(&inputs[0], float_exponent(kwargs.exp, kwargs.max_denom)?)?;
+    to_input_layout(result, inputs)
 }
 
-#[polars_expr(output_type_func=quantity_output)]
-fn sqrt(inputs: &[Series]) -> PolarsResult<Series> {
-    apply_unary(
-        &inputs[0],
-        col("value").sqrt().alias("result"),
-        Some(|u: Units| u.sqrt()),
+/// Raise a dimensionless quantity to a column of exponents, which are plain numbers or dimensionless quantities. A
+/// quantity with a unit can't have a different power per row
+fn pow_column_quantity(base: &Series, exponent: &Series) -> PolarsResult<Series> {
+    // the kernel computes with floats, integer values are converted as floats
+    let (base, exponent) = binary_operands(&with_float_values(base)?, &with_float_values(exponent)?)?;
+    let dimensionless = Units::dimensionless();
+    let base_unit = first_unit(&base)?;
+    let base = to_unit(&base, &dimensionless).map_err(
+        |error| polars_err!(InvalidOperation: "A column exponent requires a dimensionless base, got {}: {}", base_unit, error),
+    )?;
+    let exponent_unit = first_unit(&exponent)?;
+    let exponent = to_unit(&exponent, &dimensionless).map_err(
+        |error| polars_err!(InvalidOperation: "Exponents must be dimensionless, got {}: {}", exponent_unit, error),
+    )?;
+    apply_binary(&base, &exponent, pow_column_kernel, Some(|_, _| Units::dimensionless()))
+}
+
+/// Float powers of a column of exponents, either can be a single row
+fn pow_column_kernel(base: &Series, exponent: &Series) -> PolarsResult<Series> {
+    let (base, exponent) = (base.cast(&DataType::Float64)?, exponent.cast(&DataType::Float64)?);
+    let result: Float64Chunked = broadcast_binary_elementwise(base.f64()?, exponent.f64()?, |b, e| Some(b?.powf(e?)));
+    Ok(result.into_series())
+}
+
+fn pow_column_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    // the kernel computes with floats, whether the operands are converted or not
+    let unit_rule = |units: &[Units]| {
+        let dimensionless = Units::dimensionless();
+        in_unit_rule(&units[..1], "a dimensionless base", &dimensionless, &dimensionless)?;
+        in_unit_rule(&units[1..], "dimensionless exponents", &dimensionless, &dimensionless)
+    };
+    binary_output(input_fields, pow_column_kernel, unit_rule, |units| {
+        vec![false; units.len()]
+    })
+}
+
+#[polars_expr(output_type_func=pow_column_output)]
+fn pow_column(inputs: &[Series]) -> PolarsResult<Series> {
+    let result = pow_column_quantity(&inputs[0], &inputs[1])?;
+    to_input_layout(result, inputs)
+}
+
+fn sqrt_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    unary_output(
+        input_fields,
+        |s| float_kernel(s, f64::sqrt),
+        |units| Ok(units[0].sqrt()),
     )
 }
 
-create_unit_unary_expr!(abs);
-create_unit_binary_expr!(add);
-create_unit_unary_expr!(arccos);
-create_unit_unary_expr!(arccosh);
-create_unit_unary_expr!(arcsin);
-create_unit_unary_expr!(arcsinh);
-create_unit_unary_expr!(arctan);
-create_unit_unary_expr!(arctanh);
-// create_unit_expr!(backward_fill);
-// create_unit_expr!(cast);
-create_unit_unary_expr!(cbrt);
-// create_unit_expr!(ceil);
-// create_unit_expr!(clip);
-create_unit_unary_expr!(cos);
-create_unit_unary_expr!(cosh);
-create_unit_unary_expr!(cot);
-// create_unit_unary_expr!(cum_count);
-create_unit_unary_expr!(cum_max, false);
-create_unit_unary_expr!(cum_min, false);
-create_unit_unary_expr!(cum_prod, false);
-// create_unit_unary_expr!(diff);
-
-// create_unit_binary_expr!(dot);
-create_unit_unary_expr!(neg);
-// create_unit_unary_expr!(exp);
-// create_unit_unary_expr!(expm1);
-// create_unit_unary_expr!(floor);
-// create_unit_unary_expr!(log);
-// create_unit_unary_expr!(log1p);
-// create_unit_unary_expr!(log10);
-// create_unit_unary_expr!(log2);
-// create_unit_unary_expr!(round);
-// create_unit_unary_expr!(sign);
-create_unit_unary_expr!(tan);
-create_unit_unary_expr!(tanh);
-create_unit_binary_expr!(sub);
-create_unit_unary_expr!(sin);
-create_unit_binary_expr!(mul, |a, b| a.multiply(&b));
-create_unit_binary_expr!(div, |a, b| a.divide(&b));
-
-create_unit_unary_expr!(min);
-create_unit_unary_expr!(max);
-create_unit_unary_expr!(mean);
-create_unit_unary_expr!(median);
-create_unit_unary_expr!(std, 1);
-create_unit_unary_expr!(var, 1);
-create_unit_unary_expr!(sum);
+#[polars_expr(output_type_func=sqrt_output)]
+fn sqrt(inputs: &[Series]) -> PolarsResult<Series> {
+    let result = apply_unary(&inputs[0], |s| float_kernel(s, f64::sqrt), Some(|u: Units| u.sqrt()))?;
+    to_input_layout(result, inputs)
+}
 
 #[derive(Deserialize)]
-struct ConvertKwarg {
-    to: String,
+struct RoundKwarg {
+    decimals: u32,
 }
 
-#[polars_expr(output_type_func=quantity_output)]
-fn convert(inputs: &[Series], kwargs: ConvertKwarg) -> PolarsResult<Series> {
-    let (value, unit) = extract_quantity(&inputs[0])?;
-    let from_name = Units::from_scalar(unit.first())?.units[0].name.clone();
-    // TODO: support conversion from derive units, like m/s
-    // verbose error handling...
-    let conv_factor = match REGISTRY.convert(from_name, kwargs.to.clone()) {
-        Ok(factor) => Ok(factor),
-        Err(error) => Err(PolarsError::ComputeError(format!("{}", error).into())),
-    }?;
-    let value = value * conv_factor;
-    let new_unit = Units::new_simple(&kwargs.to).to_scalar()?;
-    add_unit(value, new_unit)
+fn round_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    unary_output(input_fields, |s| s.round(0), keep_unit)
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+#[polars_expr(output_type_func=round_output)]
+fn round(inputs: &[Series], kwargs: RoundKwarg) -> PolarsResult<Series> {
+    let result = apply_unary::<fn(Units) -> Units>(&inputs[0], |s| s.round(kwargs.decimals), None)?;
+    to_input_layout(result, inputs)
+}
 
-    #[test]
-    fn test_pow_int() {
-        let unit_dtype = DataType::List(Box::new(DataType::Struct(vec![
-            Field::new("name".into(), DataType::String),
-            Field::new(
-                "power".into(),
-                DataType::Struct(vec![
-                    Field::new("numer".into(), DataType::Int64),
-                    Field::new("denom".into(), DataType::Int64),
-                ]),
-            ),
-        ])));
+fn sign_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    unary_output(input_fields, sign_kernel, |_| Ok(Units::dimensionless()))
+}
 
-        let quantity_dtype = DataType::Struct(vec![
-            Field::new("value".into(), DataType::Int32),
-            Field::new("unit".into(), unit_dtype.clone()),
-        ]);
+/// The sign of a quantity is a dimensionless -1, 0 or 1
+#[polars_expr(output_type_func=sign_output)]
+fn sign(inputs: &[Series]) -> PolarsResult<Series> {
+    let result = apply_unary(&inputs[0], sign_kernel, Some(|_: Units| Units::dimensionless()))?;
+    to_input_layout(result, inputs)
+}
 
-        let unit = df!(
-            "name" => &["m", "m", "m"],
-            "power" => df!(
-                "numer"=> Series::new("numer".into(), &[1, 1, 1]).cast(&DataType::Int64).unwrap(),
-                "denom"=> Series::new("denom".into(), &[1, 1, 1]).cast(&DataType::Int64).unwrap()
-            ).unwrap().into_struct("power".into()).into_series()
-        )
-        .unwrap()
-        .into_struct("unit".into())
-        .into_series();
+#[derive(Deserialize)]
+struct ClipKwarg {
+    has_lower: bool,
+    has_upper: bool,
+}
 
-        let s = df!(
-            "value" => &[1,2,3],
-            "unit" => &[unit.clone(), unit.clone(), unit.clone()]
-        )
-        .unwrap()
-        .into_struct("quantity".into())
-        .into_series();
+/// Clip a quantity to bounds, which are quantities converted to the unit of the clipped quantity
+fn clip_quantity(input: &Series, lower: Option<&Series>, upper: Option<&Series>) -> PolarsResult<Series> {
+    let operands: Vec<Series> = [Some(input), lower, upper].into_iter().flatten().cloned().collect();
+    let mut operands = float_operands(&operands, bound_conversions)?.into_iter();
+    let input = operands.next().expect("the clipped quantity");
+    let lower = lower.and_then(|_| operands.next());
+    let upper = upper.and_then(|_| operands.next());
+    let unit = first_unit(&input)?;
+    let (value, unit_col) = extract_quantity(&input)?;
+    let lower = lower.map(|bound| values_in_unit(&bound, &unit)).transpose()?;
+    let upper = upper.map(|bound| values_in_unit(&bound, &unit)).transpose()?;
+    // float bounds (e.g. converted from another unit) can't be cast to integer values
+    let value = if !value.dtype().is_float() && lower.iter().chain(&upper).any(|bound| bound.dtype().is_float()) {
+        value.cast(&DataType::Float64)?
+    } else {
+        value
+    };
+    let result = match (&lower, &upper) {
+        (Some(lower), Some(upper)) => polars::prelude::clip(&value, lower, upper)?,
+        (Some(lower), None) => polars::prelude::clip_min(&value, lower)?,
+        (None, Some(upper)) => polars::prelude::clip_max(&value, upper)?,
+        (None, None) => value,
+    };
+    add_unit(result, unit_col.first())
+}
 
-        assert_eq!(s.dtype(), &quantity_dtype);
+#[polars_expr(output_type_func=with_float_operands_output)]
+fn clip(inputs: &[Series], kwargs: ClipKwarg) -> PolarsResult<Series> {
+    let mut bounds = inputs[1..].iter();
+    let lower = if kwargs.has_lower { bounds.next() } else { None };
+    let upper = if kwargs.has_upper { bounds.next() } else { None };
+    to_input_layout(clip_quantity(&inputs[0], lower, upper)?, inputs)
+}
 
-        let s_pow = apply_unary(&s, col("value").pow(2).alias("result"), Some(|u: Units| u.pow_int(2))).unwrap();
+/// Apply a function of floats to a quantity converted to `in_unit`, `expected` describes the accepted quantities in
+/// the error raised when the quantity can't be converted
+fn apply_in_unit(
+    input: &Series,
+    name: &str,
+    expected: &str,
+    in_unit: &Units,
+    out_unit: &Units,
+    f: fn(f64) -> f64,
+) -> PolarsResult<Series> {
+    let unit = first_unit(input)?;
+    // the function is applied to floats, integer values are converted as floats
+    let input = with_float_values(input)?;
+    let (value, _) = extract_quantity(&input)?;
+    let result_dtype = value.dtype().clone();
+    let input = to_unit(&input, in_unit)
+        .map_err(|error| polars_err!(InvalidOperation: "{} expects {}, got {}: {}", name, expected, unit, error))?;
+    let (value, _) = extract_quantity(&input)?;
+    let result = value.cast(&DataType::Float64)?.f64()?.apply_values(f).into_series();
+    add_unit(result.cast(&result_dtype)?, out_unit.to_scalar()?)
+}
 
-        let expected_unit = df!(
-            "name" => &["m", "m", "m"],
-            "power" => df!(
-                "numer"=> Series::new("numer".into(), &[2, 2, 2]).cast(&DataType::Int64).unwrap(),
-                "denom"=> Series::new("denom".into(), &[1, 1, 1]).cast(&DataType::Int64).unwrap()
-            ).unwrap().into_struct("power".into()).into_series()
-        )
-        .unwrap()
-        .into_struct("unit".into())
-        .into_series();
+/// Apply a function of pure numbers (e.g. `exp` or `log`) to a quantity, which must be dimensionless once its units
+/// are reduced by the registry (e.g. `m/km`)
+fn apply_dimensionless(input: &Series, name: &str, f: fn(f64) -> f64) -> PolarsResult<Series> {
+    let dimensionless = Units::dimensionless();
+    apply_in_unit(
+        input,
+        name,
+        "a dimensionless quantity",
+        &dimensionless,
+        &dimensionless,
+        f,
+    )
+}
 
-        let expected = df!(
-            "value" => &[1, 4, 9],
-            "unit" => &[expected_unit.clone(), expected_unit.clone(), expected_unit.clone()]
-        )
-        .unwrap()
+/// Trigonometric functions take angles in any unit (e.g. `degree`), converted to radians, and return pure numbers
+fn apply_trig(input: &Series, name: &str, f: fn(f64) -> f64) -> PolarsResult<Series> {
+    let radian = Units::new_simple("radian");
+    apply_in_unit(input, name, "an angle", &radian, &Units::dimensionless(), f)
+}
+
+/// Inverse trigonometric functions take pure numbers and return angles in radians
+fn apply_inverse_trig(input: &Series, name: &str, f: fn(f64) -> f64) -> PolarsResult<Series> {
+    let radian = Units::new_simple("radian");
+    apply_in_unit(
+        input,
+        name,
+        "a dimensionless quantity",
+        &Units::dimensionless(),
+        &radian,
+        f,
+    )
+}
+
+fn dimensionless_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    float_output(input_fields, |units| {
+        let dimensionless = Units::dimensionless();
+        in_unit_rule(units, "a dimensionless quantity", &dimensionless, &dimensionless)
+    })
+}
+
+fn trig_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    float_output(input_fields, |units| {
+        in_unit_rule(units, "an angle", &Units::new_simple("radian"), &Units::dimensionless())
+    })
+}
+
+fn inverse_trig_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    float_output(input_fields, |units| {
+        in_unit_rule(
+            units,
+            "a dimensionless quantity",
+            &Units::dimensionless(),
+            &Units::new_simple("radian"),
+        )
+    })
+}
+
+macro_rules! create_dimensionless_expr {
+    ($name:ident, $f:expr) => {
+        create_dimensionless_expr!($name, $f, apply_dimensionless, dimensionless_output);
+    };
+    ($name:ident, $f:expr, $apply:ident, $output:ident) => {
+        #[polars_expr(output_type_func=$output)]
+        fn $name(inputs: &[Series]) -> PolarsResult<Series> {
+            to_input_layout($apply(&inputs[0], stringify!($name), $f)?, inputs)
+        }
+    };
+}
+
+create_dimensionless_expr!(exp, f64::exp);
+create_dimensionless_expr!(expm1, f64::exp_m1);
+create_dimensionless_expr!(log, f64::ln);
+create_dimensionless_expr!(log1p, f64::ln_1p);
+create_dimensionless_expr!(log10, f64::log10);
+create_dimensionless_expr!(log2, f64::log2);
+
+create_dimensionless_expr!(sin, f64::sin, apply_trig, trig_output);
+create_dimensionless_expr!(cos, f64::cos, apply_trig, trig_output);
+create_dimensionless_expr!(tan, f64::tan, apply_trig, trig_output);
+create_dimensionless_expr!(cot, |x| 1.0 / x.tan(), apply_trig, trig_output);
+create_dimensionless_expr!(cosh, f64::cosh, apply_trig, trig_output);
+create_dimensionless_expr!(tanh, f64::tanh, apply_trig, trig_output);
+create_dimensionless_expr!(arcsin, f64::asin, apply_inverse_trig, inverse_trig_output);
+create_dimensionless_expr!(arccos, f64::acos, apply_inverse_trig, inverse_trig_output);
+create_dimensionless_expr!(arctan, f64::atan, apply_inverse_trig, inverse_trig_output);
+create_dimensionless_expr!(arcsinh, f64::asinh, apply_inverse_trig, inverse_trig_output);
+create_dimensionless_expr!(arccosh, f64::acosh, apply_inverse_trig, inverse_trig_output);
+create_dimensionless_expr!(arctanh, f64::atanh, apply_inverse_trig, inverse_trig_output);
+
+create_unit_unary_expr!(abs, abs_output, polars::prelude::abs);
+// create_unit_expr!(backward_fill);
+// create_unit_expr!(cast);
+create_unit_unary_expr!(cbrt, cbrt_output, |s| float_kernel(s, f64::cbrt));
+create_unit_unary_expr!(ceil, ceil_output, |s| s.ceil());
+create_unit_unary_expr!(cum_max, cum_max_output, |s| polars::prelude::cum_max(s, false));
+create_unit_unary_expr!(cum_min, cum_min_output, |s| polars::prelude::cum_min(s, false));
+
+/// Unit of sums of rows, which adds them as `add_quantities`: rows in an offset unit (e.g. `degC`) can't be summed
+fn summed_unit(unit: &Units) -> PolarsResult<Units> {
+    // bound first, so that the registry is not locked while formatting the error
+    let is_offset = registry().is_offset_unit(unit);
+    if is_offset {
+        return Err(offset_addition_error(unit, unit));
+    }
+    Ok(unit.clone())
+}
+
+fn cum_sum_quantity(input: &Series) -> PolarsResult<Series> {
+    summed_unit(&first_unit(input)?)?;
+    apply_unary::<fn(Units) -> Units>(input, |s| polars::prelude::cum_sum(s, false), None)
+}
+
+fn cum_sum_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    unary_output(
+        input_fields,
+        |s| polars::prelude::cum_sum(s, false),
+        |units| summed_unit(&units[0]),
+    )
+}
+
+#[polars_expr(output_type_func=cum_sum_output)]
+fn cum_sum(inputs: &[Series]) -> PolarsResult<Series> {
+    to_input_layout(cum_sum_quantity(&inputs[0])?, inputs)
+}
+
+create_unit_unary_expr!(neg, neg_output, negate);
+create_unit_unary_expr!(floor, floor_output, |s| s.floor());
+
+create_unit_unary_expr!(min, min_output, |s| reduce(s, |s| s.min_reduce()));
+create_unit_unary_expr!(max, max_output, |s| reduce(s, |s| s.max_reduce()));
+create_unit_unary_expr!(mean, mean_output, |s| reduce(s, |s| Ok(s.mean_reduce())));
+create_unit_unary_expr!(median, median_output, |s| reduce(s, |s| s.median_reduce()));
+create_unit_unary_expr!(std, std_output, |s| reduce(s, |s| s.std_reduce(1)));
+create_unit_unary_expr!(sum, sum_output, |s| reduce(s, |s| s.sum_reduce()));
+
+/// The variance is in the square of the unit, of the difference unit for offset units (e.g. `delta_degC**2` for
+/// `degC`)
+fn var_quantity(input: &Series, ddof: u8) -> PolarsResult<Series> {
+    let new_unit = diff_unit(&first_unit(input)?)?.pow_int(2);
+    apply_unary(
+        input,
+        |s| reduce(s, |s| s.var_reduce(ddof)),
+        Some(|_: Units| new_unit.clone()),
+    )
+}
+
+fn var_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    unary_output(
+        input_fields,
+        |s| reduce(s, |s| s.var_reduce(1)),
+        |units| Ok(diff_unit(&units[0])?.pow_int(2)),
+    )
+}
+
+#[polars_expr(output_type_func=var_output)]
+fn var(inputs: &[Series]) -> PolarsResult<Series> {
+    to_input_layout(var_quantity(&inputs[0], 1)?, inputs)
+}
+
+/// Cumulative product, the k-th non-null row is in the unit to the power k. Offset units are converted to their
+/// absolute unit first, as for multiplication
+fn cum_prod_quantity(input: &Series) -> PolarsResult<Series> {
+    let input = to_absolute(&float_operands(slice::from_ref(input), absolute_conversions)?[0])?;
+    let unit = first_unit(&input)?;
+    let (value, _) = extract_quantity(&input)?;
+    let counts = polars::prelude::cum_count(&value, false)?;
+    let units = counts
+        .idx()?
+        .into_no_null_iter()
+        .map(|count| unit.pow_int(count as i64))
+        .collect();
+    add_unit_per_row(polars::prelude::cum_prod(&value, false)?, units)
+}
+
+/// Output of a product of rows, of which the unit is a power of the unit depending on the number of rows. It is only
+/// known when planning for dimensionless quantities
+fn row_product_output(
+    input_fields: &[Field],
+    kernel: impl FnOnce(&Series) -> PolarsResult<Series>,
+) -> PolarsResult<Field> {
+    let dtypes = converted_dtypes(&input_fields[..1], absolute_conversions)?;
+    let dtype = kernel_dtype(&dtypes, |values| kernel(&values[0]))?;
+    let units = planned_units(input_fields)?
+        .filter(|units| units[0] == Units::dimensionless())
+        .map(|_| Units::dimensionless());
+    quantity_field(input_fields[0].name().clone(), dtype, units)
+}
+
+/// Store a product of rows in the layout declared by `row_product_output`
+fn to_row_product_layout(result: Series, inputs: &[Series]) -> PolarsResult<Series> {
+    let typed = all_typed(inputs) && first_unit(&inputs[0])? == Units::dimensionless();
+    with_layout(result, typed)
+}
+
+fn cum_prod_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    row_product_output(input_fields, |s| polars::prelude::cum_prod(s, false))
+}
+
+#[polars_expr(output_type_func=cum_prod_output)]
+fn cum_prod(inputs: &[Series]) -> PolarsResult<Series> {
+    to_row_product_layout(cum_prod_quantity(&inputs[0])?, inputs)
+}
+
+/// Product of the non-null values, in the unit to the power of their count
+fn product_quantity(input: &Series) -> PolarsResult<Series> {
+    let input = to_absolute(&float_operands(slice::from_ref(input), absolute_conversions)?[0])?;
+    let unit = first_unit(&input)?;
+    let (value, _) = extract_quantity(&input)?;
+    let count = (value.len() - value.null_count()) as i64;
+    let result = reduce(&value, |s| s.product())?;
+    add_unit(result, unit.pow_int(count).to_scalar()?)
+}
+
+fn product_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    row_product_output(input_fields, |s| reduce(s, |s| s.product()))
+}
+
+#[polars_expr(output_type_func=product_output)]
+fn product(inputs: &[Series]) -> PolarsResult<Series> {
+    to_row_product_layout(product_quantity(&inputs[0])?, inputs)
+}
+
+#[derive(Deserialize)]
+struct DiffKwarg {
+    n: i64,
+}
+
+/// Unit of differences between quantities in `unit`, the delta unit (e.g. `delta_degC`) of offset units (e.g. `degC`)
+fn diff_unit(unit: &Units) -> PolarsResult<Units> {
+    // bound first, so that the registry is not locked while getting the delta unit
+    let is_offset = registry().is_offset_unit(unit);
+    if is_offset {
+        registry().delta_unit(unit).map_err(registry_error)
+    } else {
+        Ok(unit.clone())
+    }
+}
+
+/// Differences between rows, of offset units (e.g. `degC`) are differences (e.g. `delta_degC`)
+fn diff_quantity(input: &Series, n: i64) -> PolarsResult<Series> {
+    let new_unit = diff_unit(&first_unit(input)?)?;
+    apply_unary(
+        input,
+        |s| polars::prelude::diff(s, n, NullBehavior::Ignore),
+        Some(|_: Units| new_unit.clone()),
+    )
+}
+
+fn diff_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    let kernel = |s: &Series| polars::prelude::diff(s, 1, NullBehavior::Ignore);
+    unary_output(input_fields, kernel, |units| diff_unit(&units[0]))
+}
+
+#[polars_expr(output_type_func=diff_output)]
+fn diff(inputs: &[Series], kwargs: DiffKwarg) -> PolarsResult<Series> {
+    to_input_layout(diff_quantity(&inputs[0], kwargs.n)?, inputs)
+}
+
+#[derive(Deserialize)]
+struct ShiftKwarg {
+    n: i64,
+}
+
+/// Shift the rows of a quantity by `n`, filling with the first value of `fill` converted to the unit of the quantity
+/// or with nulls
+fn shift_quantity(input: &Series, n: i64, fill: Option<&Series>) -> PolarsResult<Series> {
+    let operands: Vec<Series> = [input].into_iter().chain(fill).cloned().collect();
+    let operands = float_operands(&operands, bound_conversions)?;
+    let (input, fill) = (&operands[0], operands.get(1));
+    let fill = fill.map(|fill| values_in_unit(fill, &first_unit(input)?)).transpose()?;
+    let kernel = |value: &Series| match &fill {
+        // float fill values (e.g. converted from another unit) can't be cast to integer values
+        Some(fill) if !value.dtype().is_float() && fill.dtype().is_float() => {
+            shift_kernel(&value.cast(&DataType::Float64)?, n, Some(fill))
+        },
+        fill => shift_kernel(value, n, fill.as_ref()),
+    };
+    apply_unary::<fn(Units) -> Units>(input, kernel, None)
+}
+
+#[polars_expr(output_type_func=with_float_operands_output)]
+fn shift(inputs: &[Series], kwargs: ShiftKwarg) -> PolarsResult<Series> {
+    to_input_layout(shift_quantity(&inputs[0], kwargs.n, inputs.get(1))?, inputs)
+}
+
+#[derive(Deserialize)]
+struct PctChangeKwarg {
+    n: i64,
+}
+
+/// Relative change between rows, a dimensionless ratio. Offset units (e.g. `degC`) are converted to absolute ones
+/// first, as ratios of values with an offset have no meaning
+fn pct_change_quantity(input: &Series, n: i64) -> PolarsResult<Series> {
+    // ratios are floats, integer values are converted as floats
+    apply_unary(
+        &to_absolute(&with_float_values(input)?)?,
+        |s| polars::prelude::pct_change(s, &Series::new("n".into(), [n])),
+        Some(|_: Units| Units::dimensionless()),
+    )
+}
+
+fn pct_change_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    let kernel = |s: &Series| polars::prelude::pct_change(s, &Series::new("n".into(), [1]));
+    unary_output(input_fields, kernel, |_| Ok(Units::dimensionless()))
+}
+
+#[polars_expr(output_type_func=pct_change_output)]
+fn pct_change(inputs: &[Series], kwargs: PctChangeKwarg) -> PolarsResult<Series> {
+    to_input_layout(pct_change_quantity(&inputs[0], kwargs.n)?, inputs)
+}
+
+fn idx_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(input_fields[0].name().clone(), IDX_DTYPE))
+}
+
+/// Apply a kernel returning row indices or counts, which have no unit
+fn apply_index(input: &Series, kernel: impl FnOnce(&Series) -> PolarsResult<Series>) -> PolarsResult<Series> {
+    let (value, _) = extract_quantity(input)?;
+    Ok(kernel(&value)?.with_name(input.name().clone()))
+}
+
+/// The number of non-null rows so far, a plain count without a unit
+#[polars_expr(output_type_func=idx_output)]
+fn cum_count(inputs: &[Series]) -> PolarsResult<Series> {
+    apply_index(&inputs[0], |s| polars::prelude::cum_count(s, false))
+}
+
+/// Index of the largest value, rows with different units are compared after converting them to the unit of the first
+/// row
+#[polars_expr(output_type_func=idx_output)]
+fn arg_max(inputs: &[Series]) -> PolarsResult<Series> {
+    apply_index(&inputs[0], |s| Ok(index_kernel(s, s.arg_max())))
+}
+
+/// Index of the smallest value, rows with different units are compared after converting them to the unit of the first
+/// row
+#[polars_expr(output_type_func=idx_output)]
+fn arg_min(inputs: &[Series]) -> PolarsResult<Series> {
+    apply_index(&inputs[0], |s| Ok(index_kernel(s, s.arg_min())))
+}
+
+#[derive(Deserialize)]
+struct FormatUnitKwarg {
+    format: String,
+}
+
+fn string_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    check_valid_quantity_dtype(input_fields[0].dtype())?;
+    Ok(Field::new(input_fields[0].name().clone(), DataType::String))
+}
+
+/// The unit of a quantity as a string column, in one of the formats of `UnitFormat`
+#[polars_expr(output_type_func=string_output)]
+fn format_unit(inputs: &[Series], kwargs: FormatUnitKwarg) -> PolarsResult<Series> {
+    let format: UnitFormat = kwargs.format.parse()?;
+    let (value, unit) = extract_quantity(&inputs[0])?;
+    let unit_str = Units::from_scalar(unit.first())?.format(format);
+    Ok(StringChunked::full(inputs[0].name().clone(), &unit_str, value.len()).into_series())
+}
+
+#[derive(Deserialize)]
+struct ConvertKwarg {
+    to: String,
+}
+
+/// Convert a quantity to another unit with the same dimensions, its values are converted as floats
+fn convert_quantity(s: &Series, to_unit: &Units) -> PolarsResult<Series> {
+    convert_in_dtype(&with_float_values(s)?, to_unit)
+}
+
+/// Convert a quantity to another unit in the dtype of its values (see `convert_values`), e.g. the right operand of
+/// `km + m`, so that the dtype of the result is the one declared when planning
+fn convert_in_dtype(s: &Series, to_unit: &Units) -> PolarsResult<Series> {
+    let (value, unit) = extract_quantity(s)?;
+    let from_unit = Units::from_scalar(unit.first())?;
+    let conversion = registry()
+        .convert_between(&from_unit, to_unit)
+        .map_err(registry_error)?;
+    let (factor, offset) = if value.dtype().is_float() {
+        conversion
+    } else {
+        integer_conversion(conversion).ok_or_else(|| integer_conversion_error(&from_unit, to_unit))?
+    };
+    add_unit(convert_values(&value, &[factor], &[offset])?, to_unit.to_scalar()?)
+}
+
+fn convert_output(input_fields: &[Field], kwargs: ConvertKwarg) -> PolarsResult<Field> {
+    let to_unit: Units = kwargs.to.parse()?;
+    float_output(input_fields, |units| {
+        check_convertible(&units[0], &to_unit)?;
+        Ok(to_unit)
+    })
+}
+
+/// Convert a quantity to another unit expression with the same dimensions (e.g. `m/s` to `km/h`)
+#[polars_expr(output_type_func_with_kwargs=convert_output)]
+fn convert(inputs: &[Series], kwargs: ConvertKwarg) -> PolarsResult<Series> {
+    to_input_layout(convert_quantity(&inputs[0], &kwargs.to.parse()?)?, inputs)
+}
+
+fn registry_error(error: anyhow::Error) -> PolarsError {
+    PolarsError::ComputeError(format!("{:#}", error).into())
+}
+
+/// The unit of the first row of a quantity, to which the other rows are converted by `extract_quantity`
+fn first_unit(s: &Series) -> PolarsResult<Units> {
+    check_valid_quantity_dtype(s.dtype())?;
+    let unit = s.struct_()?.fields_as_series()[1].first();
+    Units::from_scalar(unit)
+}
+
+/// Convert a quantity to `unit` when its unit is different, e.g. the right operand of `km + m` to `km`
+fn to_unit(s: &Series, unit: &Units) -> PolarsResult<Series> {
+    if first_unit(s)? == *unit {
+        Ok(s.clone())
+    } else {
+        convert_in_dtype(s, unit)
+    }
+}
+
+/// Quantities with an offset unit (e.g. `degC`) are converted to their absolute unit (e.g. `kelvin`), as the offset
+/// has no meaning when multiplying or dividing
+fn to_absolute(s: &Series) -> PolarsResult<Series> {
+    let unit = first_unit(s)?;
+    // bound first, so that the registry is not locked while converting
+    let absolute_unit = registry().absolute_unit(&unit).ok();
+    if let Some(absolute_unit) = absolute_unit {
+        convert_in_dtype(s, &absolute_unit)
+    } else {
+        Ok(s.clone())
+    }
+}
+
+/// The unit of `to_absolute`
+fn absolute_unit(unit: &Units) -> Units {
+    registry().absolute_unit(unit).unwrap_or_else(|_| unit.clone())
+}
+
+/// Delta unit of an offset unit (e.g. `delta_degC` for `degC`), to which a difference in `unit` is converted
+fn delta_unit_of(unit: &Units, offset_unit: &Units) -> PolarsResult<Units> {
+    if !registry().is_delta_unit(unit) {
+        polars_bail!(InvalidOperation: "Only differences (e.g. delta_degC) can be added to or subtracted from a quantity with an offset unit ({}), got {}", offset_unit, unit)
+    }
+    registry().delta_unit(offset_unit).map_err(registry_error)
+}
+
+/// Convert a difference (e.g. `delta_degF`) to the delta unit of an offset unit (e.g. `delta_degC` for `degC`)
+fn to_delta_of(s: &Series, offset_unit: &Units) -> PolarsResult<Series> {
+    let delta_unit = delta_unit_of(&first_unit(s)?, offset_unit)?;
+    convert_in_dtype(s, &delta_unit)
+}
+
+/// Error of adding quantities with offset units (e.g. `degC`), of which the sum has no meaning
+fn offset_addition_error(unit_left: &Units, unit_right: &Units) -> PolarsError {
+    polars_err!(InvalidOperation: "Cannot add two quantities with offset units ({} and {}), subtract them to get a difference or convert them to absolute units", unit_left, unit_right)
+}
+
+/// Unit of `add_quantities`, to check it when planning
+fn added_unit(unit_left: &Units, unit_right: &Units) -> PolarsResult<Units> {
+    // bound one at a time, so that the registry is not locked twice
+    let left_offset = registry().is_offset_unit(unit_left);
+    let right_offset = registry().is_offset_unit(unit_right);
+    let is_offset = (left_offset, right_offset);
+    match is_offset {
+        (true, true) => Err(offset_addition_error(unit_left, unit_right)),
+        (true, false) => {
+            check_convertible(unit_right, &delta_unit_of(unit_right, unit_left)?)?;
+            Ok(unit_left.clone())
+        },
+        (false, true) => {
+            check_convertible(unit_left, &delta_unit_of(unit_left, unit_right)?)?;
+            Ok(unit_right.clone())
+        },
+        (false, false) => {
+            check_convertible(unit_right, unit_left)?;
+            Ok(unit_left.clone())
+        },
+    }
+}
+
+/// Unit of `sub_quantities`, to check it when planning
+fn subtracted_unit(unit_left: &Units, unit_right: &Units) -> PolarsResult<Units> {
+    // bound one at a time, so that the registry is not locked twice
+    let left_offset = registry().is_offset_unit(unit_left);
+    let right_offset = registry().is_offset_unit(unit_right);
+    let is_offset = (left_offset, right_offset);
+    match is_offset {
+        (true, true) => {
+            check_convertible(unit_right, unit_left)?;
+            registry().delta_unit(unit_left).map_err(registry_error)
+        },
+        (true, false) => {
+            check_convertible(unit_right, &delta_unit_of(unit_right, unit_left)?)?;
+            Ok(unit_left.clone())
+        },
+        (false, true) => {
+            polars_bail!(InvalidOperation: "Cannot subtract a quantity with an offset unit ({}) from {}", unit_right, unit_left)
+        },
+        (false, false) => {
+            check_convertible(unit_right, unit_left)?;
+            Ok(unit_left.clone())
+        },
+    }
+}
+
+/// Addition, the right operand is converted to the unit of the left one. With pint's rules for offset units: an offset unit plus a difference is an offset unit, while adding two
+/// offset units is an error
+fn add_quantities(left: &Series, right: &Series) -> PolarsResult<Series> {
+    let operands = float_operands(&[left.clone(), right.clone()], additive_conversions)?;
+    let (left, right) = additive_operands(&operands[0], &operands[1], "add")?;
+    let (left, right) = (&left, &right);
+    let (unit_left, unit_right) = (first_unit(left)?, first_unit(right)?);
+    let kernel = |l: &Series, r: &Series| l + r;
+    // bound one at a time, so that the registry is not locked twice
+    let left_offset = registry().is_offset_unit(&unit_left);
+    let right_offset = registry().is_offset_unit(&unit_right);
+    let is_offset = (left_offset, right_offset);
+    match is_offset {
+        (true, true) => Err(offset_addition_error(&unit_left, &unit_right)),
+        (true, false) => apply_binary(left, &to_delta_of(right, &unit_left)?, kernel, Some(|a, _| a)),
+        (false, true) => apply_binary(&to_delta_of(left, &unit_right)?, right, kernel, Some(|_, b| b)),
+        (false, false) => apply_binary(left, &to_unit(right, &unit_left)?, kernel, None),
+    }
+}
+
+/// Subtraction, the right operand is converted to the unit of the left one. With pint's rules for offset units: the difference of two offset units is a delta unit
+/// (e.g. `degC - degC` is `delta_degC`) and an offset unit minus a difference is an offset unit
+fn sub_quantities(left: &Series, right: &Series) -> PolarsResult<Series> {
+    let operands = float_operands(&[left.clone(), right.clone()], additive_conversions)?;
+    let (left, right) = additive_operands(&operands[0], &operands[1], "subtract")?;
+    let (left, right) = (&left, &right);
+    let (unit_left, unit_right) = (first_unit(left)?, first_unit(right)?);
+    let kernel = |l: &Series, r: &Series| l - r;
+    // bound one at a time, so that the registry is not locked twice
+    let left_offset = registry().is_offset_unit(&unit_left);
+    let right_offset = registry().is_offset_unit(&unit_right);
+    let is_offset = (left_offset, right_offset);
+    match is_offset {
+        (true, true) => {
+            let result = apply_binary(left, &convert_in_dtype(right, &unit_left)?, kernel, None)?;
+            let (value, _) = extract_quantity(&result)?;
+            add_unit(
+                value,
+                registry().delta_unit(&unit_left).map_err(registry_error)?.to_scalar()?,
+            )
+        },
+        (true, false) => apply_binary(left, &to_delta_of(right, &unit_left)?, kernel, Some(|a, _| a)),
+        (false, true) => {
+            polars_bail!(InvalidOperation: "Cannot subtract a quantity with an offset unit ({}) from {}", unit_right, unit_left)
+        },
+        (false, false) => apply_binary(left, &to_unit(right, &unit_left)?, kernel, None),
+    }
+}
+
+/// Values of a quantity in `unit`
+fn values_in_unit(s: &Series, unit: &Units) -> PolarsResult<Series> {
+    let (value, _) = extract_quantity(&to_unit(s, unit)?)?;
+    Ok(value)
+}
+
+/// Values of a quantity in `unit` to compare them, integer values in another unit are compared as floats
+fn compared_values(s: &Series, unit: &Units) -> PolarsResult<Series> {
+    if first_unit(s)? == *unit {
+        values_in_unit(s, unit)
+    } else {
+        values_in_unit(&with_float_values(s)?, unit)
+    }
+}
+
+/// Values of two quantities in the unit of the left one, to compare them
+fn values_in_left_unit(left: &Series, right: &Series) -> PolarsResult<(Series, Series)> {
+    let unit_left = first_unit(left)?;
+    Ok((values_in_unit(left, &unit_left)?, compared_values(right, &unit_left)?))
+}
+
+/// Boolean output of comparisons, the units of the other quantities must be convertible to the unit of the first one
+fn compare_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    if let Some(units) = planned_units(input_fields)? {
+        keep_unit(&units)?;
+    }
+    Ok(Field::new(input_fields[0].name().clone(), DataType::Boolean))
+}
+
+macro_rules! create_unit_compare_expr {
+    ($name:ident, $method:ident) => {
+        /// Comparison of two quantities after converting the right one to the unit of the left one,
+        /// a quantity of length 1 (e.g. a literal) is compared to every row
+        #[polars_expr(output_type_func=compare_output)]
+        fn $name(inputs: &[Series]) -> PolarsResult<Series> {
+            let (left, right) = values_in_left_unit(&inputs[0], &inputs[1])?;
+            Ok(left
+                .$method(&right)?
+                .with_name(inputs[0].name().clone())
+                .into_series())
+        }
+    };
+}
+
+create_unit_compare_expr!(eq, equal);
+create_unit_compare_expr!(ne, not_equal);
+create_unit_compare_expr!(lt, lt);
+create_unit_compare_expr!(le, lt_eq);
+create_unit_compare_expr!(gt, gt);
+create_unit_compare_expr!(ge, gt_eq);
+
+#[derive(Deserialize)]
+struct IsBetweenKwarg {
+    closed: String,
+}
+
+/// Whether a quantity is between two bounds, `closed` is one of `both`, `left`, `right` or `none` as in polars
+fn quantity_is_between(input: &Series, lower: &Series, upper: &Series, closed: &str) -> PolarsResult<Series> {
+    let unit = first_unit(input)?;
+    let value = values_in_unit(input, &unit)?;
+    let (lower, upper) = (compared_values(lower, &unit)?, compared_values(upper, &unit)?);
+    let (above_lower, below_upper) = match closed {
+        "both" => (value.gt_eq(&lower)?, value.lt_eq(&upper)?),
+        "left" => (value.gt_eq(&lower)?, value.lt(&upper)?),
+        "right" => (value.gt(&lower)?, value.lt_eq(&upper)?),
+        "none" => (value.gt(&lower)?, value.lt(&upper)?),
+        closed => {
+            polars_bail!(InvalidOperation: "closed must be one of 'both', 'left', 'right' or 'none', got '{}'", closed)
+        },
+    };
+    Ok((&above_lower & &below_upper)
+        .with_name(input.name().clone())
+        .into_series())
+}
+
+#[polars_expr(output_type_func=compare_output)]
+fn is_between(inputs: &[Series], kwargs: IsBetweenKwarg) -> PolarsResult<Series> {
+    quantity_is_between(&inputs[0], &inputs[1], &inputs[2], &kwargs.closed)
+}
+
+#[derive(Deserialize)]
+struct IsCloseKwarg {
+    abs_tol: f64,
+    rel_tol: f64,
+}
+
+/// Whether two quantities are close, as `math.isclose`: `|a - b| <= max(rel_tol * max(|a|, |b|), abs_tol)`
+/// with `abs_tol` in the unit of the left quantity
+fn quantity_is_close(left: &Series, right: &Series, abs_tol: f64, rel_tol: f64) -> PolarsResult<Series> {
+    let (left_values, right_values) = values_in_left_unit(left, right)?;
+    let (left_values, right_values) = (
+        left_values.cast(&DataType::Float64)?,
+        right_values.cast(&DataType::Float64)?,
+    );
+    let is_close: BooleanChunked =
+        broadcast_binary_elementwise(left_values.f64()?, right_values.f64()?, |a, b| match (a, b) {
+            (Some(a), Some(b)) => Some(a == b || (a - b).abs() <= f64::max(rel_tol * a.abs().max(b.abs()), abs_tol)),
+            _ => None,
+        });
+    Ok(is_close.with_name(left.name().clone()).into_series())
+}
+
+#[polars_expr(output_type_func=compare_output)]
+fn is_close(inputs: &[Series], kwargs: IsCloseKwarg) -> PolarsResult<Series> {
+    quantity_is_close(&inputs[0], &inputs[1], kwargs.abs_tol, kwargs.rel_tol)
+}
+
+fn add_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    binary_output(
+        input_fields,
+        |l, r| l + r,
+        |units| added_unit(&units[0], &units[1]),
+        additive_conversions,
+    )
+}
+
+fn sub_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    binary_output(
+        input_fields,
+        |l, r| l - r,
+        |units| subtracted_unit(&units[0], &units[1]),
+        additive_conversions,
+    )
+}
+
+/// Unit of products, offset units are converted to their absolute unit as in `to_absolute`
+fn multiplied_unit(units: &[Units]) -> PolarsResult<Units> {
+    Ok(absolute_unit(&units[0]).multiply(&absolute_unit(&units[1])))
+}
+
+fn mul_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    binary_output(input_fields, |l, r| l * r, multiplied_unit, absolute_conversions)
+}
+
+fn div_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    binary_output(
+        input_fields,
+        |l, r| l / r,
+        |units| Ok(absolute_unit(&units[0]).divide(&absolute_unit(&units[1]))),
+        absolute_conversions,
+    )
+}
+
+/// Plain numeric operands (e.g. `2` in `speed * 2`) are dimensionless quantities
+fn as_quantity(s: &Series) -> PolarsResult<Series> {
+    if s.dtype().is_numeric() {
+        let quantity = add_unit(s.clone().with_name("value".into()), Units::dimensionless().to_scalar()?)?;
+        Ok(quantity.with_name(s.name().clone()))
+    } else {
+        Ok(s.clone())
+    }
+}
+
+/// Operands of arithmetic as quantities of the same length, an operand of length 1 (e.g. a literal) is broadcast
+fn binary_operands(left: &Series, right: &Series) -> PolarsResult<(Series, Series)> {
+    let (left, right) = (as_quantity(left)?, as_quantity(right)?);
+    match (left.len(), right.len()) {
+        (1, len) if len != 1 => Ok((left.new_from_index(0, len), right)),
+        (len, 1) if len != 1 => Ok((left, right.new_from_index(0, len))),
+        _ => Ok((left, right)),
+    }
+}
+
+/// Operands of addition or subtraction, a plain number can only be added to or subtracted from a dimensionless
+/// quantity (e.g. `m/km`)
+fn additive_operands(left: &Series, right: &Series, op: &str) -> PolarsResult<(Series, Series)> {
+    let quantity = match (left.dtype().is_numeric(), right.dtype().is_numeric()) {
+        (true, false) => Some(right),
+        (false, true) => Some(left),
+        _ => None,
+    };
+    if let Some(quantity) = quantity {
+        let unit = first_unit(quantity)?;
+        // bound first, so that the registry is not locked while converting
+        let is_dimensionless = registry().convert_between(&unit, &Units::dimensionless()).is_ok();
+        if !is_dimensionless {
+            polars_bail!(InvalidOperation: "Cannot {} a plain number and a quantity in {}, which is not dimensionless", op, unit)
+        }
+    }
+    binary_operands(left, right)
+}
+
+#[polars_expr(output_type_func=add_output)]
+fn add(inputs: &[Series]) -> PolarsResult<Series> {
+    to_input_layout(add_quantities(&inputs[0], &inputs[1])?, inputs)
+}
+
+#[polars_expr(output_type_func=sub_output)]
+fn sub(inputs: &[Series]) -> PolarsResult<Series> {
+    to_input_layout(sub_quantities(&inputs[0], &inputs[1])?, inputs)
+}
+
+fn mul_quantities(left: &Series, right: &Series) -> PolarsResult<Series> {
+    let operands = float_operands(&[left.clone(), right.clone()], absolute_conversions)?;
+    let (left, right) = binary_operands(&operands[0], &operands[1])?;
+    apply_binary(
+        &to_absolute(&left)?,
+        &to_absolute(&right)?,
+        |l, r| l * r,
+        Some(|a, b| a.multiply(&b)),
+    )
+}
+
+#[polars_expr(output_type_func=mul_output)]
+fn mul(inputs: &[Series]) -> PolarsResult<Series> {
+    to_input_layout(mul_quantities(&inputs[0], &inputs[1])?, inputs)
+}
+
+fn div_quantities(left: &Series, right: &Series) -> PolarsResult<Series> {
+    let operands = float_operands(&[left.clone(), right.clone()], absolute_conversions)?;
+    let (left, right) = binary_operands(&operands[0], &operands[1])?;
+    apply_binary(
+        &to_absolute(&left)?,
+        &to_absolute(&right)?,
+        |l, r| l / r,
+        Some(|a, b| a.divide(&b)),
+    )
+}
+
+#[polars_expr(output_type_func=div_output)]
+fn div(inputs: &[Series]) -> PolarsResult<Series> {
+    to_input_layout(div_quantities(&inputs[0], &inputs[1])?, inputs)
+}
+
+fn dot_kernel(left: &Series, right: &Series) -> PolarsResult<Series> {
+    reduce(&(left * right)?, |s| s.sum_reduce())
+}
+
+/// Dot product of two quantities, in the product of their units. Offset units are converted to their absolute unit
+/// first, as for multiplication
+fn dot_quantities(left: &Series, right: &Series) -> PolarsResult<Series> {
+    let operands = float_operands(&[left.clone(), right.clone()], absolute_conversions)?;
+    apply_binary(
+        &to_absolute(&operands[0])?,
+        &to_absolute(&operands[1])?,
+        dot_kernel,
+        Some(|a, b| a.multiply(&b)),
+    )
+}
+
+fn dot_output(input_fields: &[Field]) -> PolarsResult<Field> {
+    binary_output(input_fields, dot_kernel, multiplied_unit, absolute_conversions)
+}
+
+#[polars_expr(output_type_func=dot_output)]
+fn dot(inputs: &[Series]) -> PolarsResult<Series> {
+    to_input_layout(dot_quantities(&inputs[0], &inputs[1])?, inputs)
+}
+
+#[cfg(test)]
+mod test {
+    use std::slice;
+
+    use num_rational::Rational64;
+
+    use super::*;
+
+    #[test]
+    fn test_pow_int() {
+        let unit_dtype = DataType::List(Box::new(DataType::Struct(vec![
+            Field::new("name".into(), DataType::String),
+            Field::new(
+                "power".into(),
+                DataType::Struct(vec![
+                    Field::new("numer".into(), DataType::Int64),
+                    Field::new("denom".into(), DataType::Int64),
+                ]),
+            ),
+        ])));
+
+        let quantity_dtype = DataType::Struct(vec![
+            Field::new("value".into(), DataType::Int32),
+            Field::new("unit".into(), unit_dtype.clone()),
+        ]);
+
+        let unit = df!(
+            "name" => &["m"],
+            "power" => df!(
+                "numer"=> Series::new("numer".into(), &[1]).cast(&DataType::Int64).unwrap(),
+                "denom"=> Series::new("denom".into(), &[1]).cast(&DataType::Int64).unwrap()
+            ).unwrap().into_struct("power".into()).into_series()
+        )
+        .unwrap()
+        .into_struct("unit".into())
+        .into_series();
+
+        let s = df!(
+            "value" => &[1,2,3],
+            "unit" => &[unit.clone(), unit.clone(), unit.clone()]
+        )
+        .unwrap()
         .into_struct("quantity".into())
         .into_series();
 
-        assert_eq!(s_pow.eq(&expected), true);
+        assert_eq!(s.dtype(), &quantity_dtype);
+
+        let s_pow = apply_unary(&s, |s| pow_int_kernel(s, 2), Some(|u: Units| u.pow_int(2))).unwrap();
+
+        // the result is in the default layout
+        let expected = df!(
+            "value" => &[1, 4, 9],
+            "unit" => &["m^2", "m^2", "m^2"]
+        )
+        .unwrap()
+        .into_struct("quantity".into())
+        .into_series();
+
+        assert!(s_pow.eq(&expected));
+    }
+
+    fn quantity(values: &[f64], unit: Units) -> Series {
+        add_unit(Series::new("value".into(), values), unit.to_scalar().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_add_same_units_in_different_order() {
+        let ms = Units {
+            units: vec![
+                Unit {
+                    name: "m".to_string(),
+                    power: Rational64::from_integer(1),
+                },
+                Unit {
+                    name: "s".to_string(),
+                    power: Rational64::from_integer(1),
+                },
+            ],
+        };
+        let sm = Units {
+            units: ms.units.iter().rev().cloned().collect(),
+        };
+        let left = quantity(&[1.0, 2.0], ms.clone());
+        // bypass the canonicalization of `to_scalar`
+        let right = add_unit(
+            Series::new("value".into(), &[3.0, 4.0]),
+            Scalar::new(
+                Units::list_dtype(),
+                AnyValue::List(
+                    df!("name" => &["s", "m"], "power" => df!("numer" => &[1i64, 1], "denom" => &[1i64, 1]).unwrap().into_struct("power".into()).into_series())
+                        .unwrap()
+                        .into_struct("unit".into())
+                        .into_series(),
+                ),
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            Units::from_scalar(extract_quantity(&right).unwrap().1.first())
+                .unwrap()
+                .units,
+            sm.units
+        );
+
+        let result = apply_binary(&left, &right, |l, r| l + r, None).unwrap();
+        let (value, unit) = extract_quantity(&result).unwrap();
+        assert_eq!(value, Series::new("value".into(), &[4.0, 6.0]));
+        assert_eq!(Units::from_scalar(unit.first()).unwrap(), ms);
+    }
+
+    #[test]
+    fn test_convert_compound_units() {
+        let input = quantity(&[1.0, 10.0], "m/s".parse().unwrap());
+        let result = convert_quantity(&input, &"km/h".parse().unwrap()).unwrap();
+        let (value, unit) = extract_quantity(&result).unwrap();
+        let value: Vec<f64> = value.f64().unwrap().into_no_null_iter().collect();
+        assert!(is_close::all_close!(value, [3.6, 36.0]));
+        assert_eq!(Units::from_scalar(unit.first()).unwrap(), "km/h".parse().unwrap());
+    }
+
+    #[test]
+    fn test_convert_offset_units() {
+        let input = quantity(&[32.0, 212.0], "degF".parse().unwrap());
+        let result = convert_quantity(&input, &"K".parse().unwrap()).unwrap();
+        let value: Vec<f64> = extract_quantity(&result)
+            .unwrap()
+            .0
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert!(is_close::all_close!(value, [273.15, 373.15]));
+    }
+
+    #[test]
+    fn test_add_sub_convert_right_operand() {
+        let left = quantity(&[1.0, 2.0], Units::new_simple("km"));
+        let right = quantity(&[500.0, 1.0], Units::new_simple("m"));
+        let result = add_quantities(&left, &right).unwrap();
+        assert!(is_close::all_close!(values(&result), [1.5, 2.001]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("km"));
+        let result = sub_quantities(&right, &left).unwrap();
+        assert!(is_close::all_close!(values(&result), [-500.0, -1999.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("m"));
+
+        let error = add_quantities(&left, &quantity(&[1.0], Units::new_simple("s"))).unwrap_err();
+        assert!(error.to_string().contains("different dimensions"), "{}", error);
+    }
+
+    fn booleans(s: &Series) -> Vec<Option<bool>> {
+        s.bool().unwrap().into_iter().collect()
+    }
+
+    #[test]
+    fn test_compare_quantities() {
+        let lengths = quantity(&[500.0, 1000.0, 1500.0], Units::new_simple("m"));
+        let threshold = quantity(&[1.0], Units::new_simple("km"));
+        let (left, right) = values_in_left_unit(&lengths, &threshold).unwrap();
+        assert_eq!(
+            booleans(&left.lt(&right).unwrap().into_series()),
+            [Some(true), Some(false), Some(false)]
+        );
+        assert_eq!(
+            booleans(&left.equal(&right).unwrap().into_series()),
+            [Some(false), Some(true), Some(false)]
+        );
+        assert!(values_in_left_unit(&lengths, &quantity(&[1.0], Units::new_simple("s"))).is_err());
+    }
+
+    #[test]
+    fn test_is_between() {
+        let lengths = quantity(&[500.0, 1000.0, 1500.0], Units::new_simple("m"));
+        let (lower, upper) = (
+            quantity(&[0.5], Units::new_simple("km")),
+            quantity(&[100_000.0], Units::new_simple("cm")),
+        );
+        let cases = [
+            ("both", [Some(true), Some(true), Some(false)]),
+            ("left", [Some(true), Some(false), Some(false)]),
+            ("right", [Some(false), Some(true), Some(false)]),
+            ("none", [Some(false), Some(false), Some(false)]),
+        ];
+        for (closed, expected) in cases {
+            let result = quantity_is_between(&lengths, &lower, &upper, closed).unwrap();
+            assert_eq!(booleans(&result), expected, "{}", closed);
+        }
+        assert!(quantity_is_between(&lengths, &lower, &upper, "open").is_err());
+    }
+
+    #[test]
+    fn test_is_close() {
+        let left = quantity(&[1.0, 1.0, 0.0], Units::new_simple("km"));
+        let right = quantity(&[1000.0, 1001.0, 1e-3], Units::new_simple("m"));
+        let result = quantity_is_close(&left, &right, 0.0, 1e-9).unwrap();
+        assert_eq!(booleans(&result), [Some(true), Some(false), Some(false)]);
+        // abs_tol is in the unit of the left quantity
+        let result = quantity_is_close(&left, &right, 1e-5, 1e-3).unwrap();
+        assert_eq!(booleans(&result), [Some(true), Some(true), Some(true)]);
+    }
+
+    #[test]
+    fn test_clip_converts_bounds() {
+        let lengths = quantity(&[0.1, 0.5, 2.0], Units::new_simple("km"));
+        let (lower, upper) = (
+            quantity(&[200.0], Units::new_simple("m")),
+            quantity(&[100_000.0], Units::new_simple("cm")),
+        );
+        let result = clip_quantity(&lengths, Some(&lower), Some(&upper)).unwrap();
+        assert!(is_close::all_close!(values(&result), [0.2, 0.5, 1.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("km"));
+        let result = clip_quantity(&lengths, None, Some(&upper)).unwrap();
+        assert!(is_close::all_close!(values(&result), [0.1, 0.5, 1.0]));
+        let result = clip_quantity(&lengths, Some(&lower), None).unwrap();
+        assert!(is_close::all_close!(values(&result), [0.2, 0.5, 2.0]));
+        assert!(clip_quantity(&lengths, Some(&quantity(&[1.0], Units::new_simple("s"))), None).is_err());
+    }
+
+    #[test]
+    fn test_clip_integer_values() {
+        let lengths = add_unit(
+            Series::new("value".into(), [1i64, 500, 2000]),
+            Units::new_simple("m").to_scalar().unwrap(),
+        )
+        .unwrap();
+        let upper = quantity(&[1.5], Units::new_simple("km"));
+        let result = clip_quantity(&lengths, None, Some(&upper)).unwrap();
+        assert!(is_close::all_close!(values(&result), [1.0, 500.0, 1500.0]));
+    }
+
+    #[test]
+    fn test_apply_dimensionless() {
+        let ratio = quantity(&[1.0, 1000.0], "m/km".parse().unwrap());
+        let result = apply_dimensionless(&ratio, "log10", f64::log10).unwrap();
+        assert!(is_close::all_close!(values(&result), [-3.0, 0.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::dimensionless());
+
+        let pure = quantity(&[0.0, 1.0], Units::dimensionless());
+        let result = apply_dimensionless(&pure, "exp", f64::exp).unwrap();
+        assert!(is_close::all_close!(values(&result), [1.0, std::f64::consts::E]));
+
+        let error = apply_dimensionless(&quantity(&[5.0], Units::new_simple("m")), "log", f64::ln).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("log expects a dimensionless quantity, got m"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_apply_trig() {
+        let angle = quantity(&[30.0, 90.0], Units::new_simple("degree"));
+        let result = apply_trig(&angle, "sin", f64::sin).unwrap();
+        assert!(is_close::all_close!(values(&result), [0.5, 1.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::dimensionless());
+
+        let pure = quantity(&[0.0], Units::dimensionless());
+        let result = apply_trig(&pure, "cos", f64::cos).unwrap();
+        assert!(is_close::all_close!(values(&result), [1.0]));
+
+        let error = apply_trig(&quantity(&[5.0], Units::new_simple("m")), "sin", f64::sin).unwrap_err();
+        assert!(error.to_string().contains("sin expects an angle, got m"), "{}", error);
+    }
+
+    #[test]
+    fn test_apply_inverse_trig() {
+        let ratio = quantity(&[0.5, 1.0], Units::dimensionless());
+        let result = apply_inverse_trig(&ratio, "arcsin", f64::asin).unwrap();
+        let pi = std::f64::consts::PI;
+        assert!(is_close::all_close!(values(&result), [pi / 6.0, pi / 2.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("radian"));
+
+        let result = apply_inverse_trig(&quantity(&[50.0], "cm/m".parse().unwrap()), "arcsin", f64::asin).unwrap();
+        assert!(is_close::all_close!(values(&result), [pi / 6.0]));
+
+        let error = apply_inverse_trig(&quantity(&[0.5], Units::new_simple("s")), "arccos", f64::acos).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("arccos expects a dimensionless quantity, got s"),
+            "{}",
+            error
+        );
+    }
+
+    fn nullable_values(s: &Series) -> Vec<Option<f64>> {
+        extract_quantity(s).unwrap().0.f64().unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_diff() {
+        let length = quantity(&[1.0, 3.0, 6.0], Units::new_simple("m"));
+        let result = diff_quantity(&length, 1).unwrap();
+        assert_eq!(nullable_values(&result), [None, Some(2.0), Some(3.0)]);
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("m"));
+
+        let temperature = quantity(&[20.0, 25.0], "degC".parse().unwrap());
+        let result = diff_quantity(&temperature, 1).unwrap();
+        assert_eq!(nullable_values(&result), [None, Some(5.0)]);
+        assert_eq!(first_unit(&result).unwrap(), "delta_degC".parse().unwrap());
+    }
+
+    #[test]
+    fn test_cum_sum_offset_units() {
+        let temperature = quantity(&[20.0, 30.0], "degC".parse().unwrap());
+        let error = cum_sum_quantity(&temperature).unwrap_err().to_string();
+        assert!(
+            error.contains("Cannot add two quantities with offset units"),
+            "{}",
+            error
+        );
+        let field = typed_field("degC");
+        assert!(cum_sum_output(slice::from_ref(&field)).is_err());
+
+        let delta = quantity(&[20.0, 30.0], "delta_degC".parse().unwrap());
+        let result = cum_sum_quantity(&delta).unwrap();
+        assert_eq!(values(&result), [20.0, 50.0]);
+        assert_eq!(first_unit(&result).unwrap(), "delta_degC".parse().unwrap());
+    }
+
+    #[test]
+    fn test_shift() {
+        let length = quantity(&[1.0, 2.0, 3.0], Units::new_simple("m"));
+        let result = shift_quantity(&length, 1, None).unwrap();
+        assert_eq!(nullable_values(&result), [None, Some(1.0), Some(2.0)]);
+
+        let fill = quantity(&[50.0], Units::new_simple("cm"));
+        let result = shift_quantity(&length, -2, Some(&fill)).unwrap();
+        assert_eq!(nullable_values(&result), [Some(3.0), Some(0.5), Some(0.5)]);
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("m"));
+    }
+
+    #[test]
+    fn test_pct_change() {
+        let length = quantity(&[1.0, 2.0, 3.0], Units::new_simple("m"));
+        let result = pct_change_quantity(&length, 1).unwrap();
+        assert_eq!(nullable_values(&result), [None, Some(1.0), Some(0.5)]);
+        assert_eq!(first_unit(&result).unwrap(), Units::dimensionless());
+    }
+
+    /// The unit of each row, without converting them to the unit of the first row
+    fn units_per_row(s: &Series) -> Vec<Units> {
+        let unit = s.struct_().unwrap().field_by_name("unit").unwrap();
+        (0..unit.len())
+            .map(|idx| {
+                let row = unit.get(idx).unwrap().into_static();
+                Units::from_scalar(Scalar::new(unit.dtype().clone(), row)).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_var() {
+        let length = quantity(&[1.0, 2.0, 3.0], Units::new_simple("m"));
+        let result = var_quantity(&length, 1).unwrap();
+        assert_eq!(values(&result), [1.0]);
+        assert_eq!(first_unit(&result).unwrap(), "m^2".parse().unwrap());
+
+        let temperature = quantity(&[20.0, 22.0], "degC".parse().unwrap());
+        let result = var_quantity(&temperature, 1).unwrap();
+        assert_eq!(first_unit(&result).unwrap(), "delta_degC^2".parse().unwrap());
+    }
+
+    #[test]
+    fn test_cum_prod() {
+        let length = quantity(&[1.0, 2.0, 3.0], Units::new_simple("m"));
+        let result = cum_prod_quantity(&length).unwrap();
+        let value: Vec<f64> = result
+            .struct_()
+            .unwrap()
+            .field_by_name("value")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(value, [1.0, 2.0, 6.0]);
+        let expected: Vec<Units> = ["m", "m^2", "m^3"].iter().map(|unit| unit.parse().unwrap()).collect();
+        assert_eq!(units_per_row(&result), expected);
+
+        let ratio = quantity(&[2.0, 3.0], Units::dimensionless());
+        let result = cum_prod_quantity(&ratio).unwrap();
+        assert_eq!(values(&result), [2.0, 6.0]);
+        assert_eq!(first_unit(&result).unwrap(), Units::dimensionless());
+    }
+
+    #[test]
+    fn test_product() {
+        let length = quantity(&[1.0, 2.0, 3.0], Units::new_simple("m"));
+        let result = product_quantity(&length).unwrap();
+        assert_eq!(values(&result), [6.0]);
+        assert_eq!(first_unit(&result).unwrap(), "m^3".parse().unwrap());
+
+        let temperature = quantity(&[0.0, 0.0], "degC".parse().unwrap());
+        let result = product_quantity(&temperature).unwrap();
+        assert!(is_close::all_close!(values(&result), [273.15 * 273.15]));
+        assert_eq!(first_unit(&result).unwrap(), "kelvin^2".parse().unwrap());
+    }
+
+    #[test]
+    fn test_arg_max_min() {
+        let input = quantity_per_row(&[1.0, 500.0, 2.0], &["m", "cm", "m"]);
+        let result = apply_index(&input, |s| Ok(index_kernel(s, s.arg_max()))).unwrap();
+        assert_eq!(result.dtype(), &IDX_DTYPE);
+        assert_eq!(result.idx().unwrap().get(0), Some(1));
+        let result = apply_index(&input, |s| Ok(index_kernel(s, s.arg_min()))).unwrap();
+        assert_eq!(result.idx().unwrap().get(0), Some(0));
+    }
+
+    #[test]
+    fn test_dot() {
+        let force = quantity(&[1.0, 2.0], Units::new_simple("N"));
+        let distance = quantity(&[3.0, 4.0], Units::new_simple("m"));
+        let result = dot_quantities(&force, &distance).unwrap();
+        assert_eq!(values(&result), [11.0]);
+        assert_eq!(first_unit(&result).unwrap(), "N*m".parse().unwrap());
+
+        let distance = quantity_per_row(&[300.0, 4.0], &["cm", "m"]);
+        let result = dot_quantities(&force, &distance).unwrap();
+        assert_eq!(values(&result), [1100.0]);
+        assert_eq!(first_unit(&result).unwrap(), "N*cm".parse().unwrap());
+    }
+
+    #[test]
+    fn test_plain_operands() {
+        let speed = quantity(&[10.0, 20.0], "m/s".parse().unwrap());
+        let two = Series::new("literal".into(), [2i64]);
+        let result = mul_quantities(&speed, &two).unwrap();
+        assert_eq!(values(&result), [20.0, 40.0]);
+        assert_eq!(first_unit(&result).unwrap(), "m/s".parse().unwrap());
+
+        let count = Series::new("count".into(), [1.0, 4.0]);
+        let result = div_quantities(&count, &speed).unwrap();
+        assert_eq!(values(&result), [0.1, 0.2]);
+        assert_eq!(first_unit(&result).unwrap(), "s/m".parse().unwrap());
+
+        let ratio = quantity(&[500.0], "m/km".parse().unwrap());
+        let (left, right) = additive_operands(&ratio, &two, "add").unwrap();
+        let result = add_quantities(&left, &right).unwrap();
+        assert_eq!(values(&result), [2500.0]);
+
+        let error = additive_operands(&speed, &two, "add").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Cannot add a plain number and a quantity in m/s"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_pow_rat() {
+        let volume = quantity(&[8.0, 27.0], "m^3".parse().unwrap());
+        let result = pow_rat_quantity(&volume, Rational64::new(1, 3)).unwrap();
+        assert!(is_close::all_close!(values(&result), [2.0, 3.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("m"));
+    }
+
+    #[test]
+    fn test_pow_column() {
+        let base = quantity(&[2.0, 2.0, 4.0], "m/km".parse().unwrap());
+        let exponent = Series::new("exponent".into(), [1i64, 2, 0]);
+        let result = pow_column_quantity(&base, &exponent).unwrap();
+        assert!(is_close::all_close!(values(&result), [0.002, 0.000004, 1.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::dimensionless());
+
+        let base = quantity(&[2.0], Units::new_simple("m"));
+        let error = pow_column_quantity(&base, &exponent).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("A column exponent requires a dimensionless base, got m"),
+            "{}",
+            error
+        );
+    }
+
+    /// The dtypes declared by the output type functions are those of the results
+    #[test]
+    fn test_output_dtypes() {
+        let m = Units::new_simple("m");
+        let ratio_unit: Units = "m/km".parse().unwrap();
+        let degree = Units::new_simple("degree");
+        // with typed quantities, the declared dtypes also include the units of the results
+        for typed in [false, true] {
+            for dtype in [
+                DataType::Int16,
+                DataType::Int32,
+                DataType::Int64,
+                DataType::Float32,
+                DataType::Float64,
+            ] {
+                let typed_quantity = |values: &[f64], unit: &Units| {
+                    let value = Series::new("value".into(), values).cast(&dtype).unwrap();
+                    with_layout(add_unit(value, unit.to_scalar().unwrap()).unwrap(), typed).unwrap()
+                };
+                let typed_field = |name: &str, dtype: &DataType, unit: &Units| {
+                    quantity_field(name.into(), dtype.clone(), typed.then(|| unit.clone())).unwrap()
+                };
+                let laid_out = |result: PolarsResult<Series>| result.and_then(|result| with_layout(result, typed));
+                let input = typed_quantity(&[1.0, 2.0, 4.0], &m);
+                let ratio = typed_quantity(&[1.0, 2.0, 4.0], &ratio_unit);
+                let angle = typed_quantity(&[1.0, 2.0, 4.0], &degree);
+                // operands converted to another unit
+                let (km, degc) = (Units::new_simple("km"), Units::new_simple("degC"));
+                let distance = typed_quantity(&[1.0, 2.0, 4.0], &km);
+                let temperature = typed_quantity(&[20.0, 25.0, 30.0], &degc);
+                let distance_field = typed_field("distance", &dtype, &km);
+                let temperature_field = typed_field("temperature", &dtype, &degc);
+                let fill = typed_quantity(&[50.0], &Units::new_simple("cm"));
+                let fill_field = typed_field("fill", &dtype, &Units::new_simple("cm"));
+                let field = typed_field("value", &dtype, &m);
+                let single = [field.clone()];
+                let bound = typed_field("bound", &DataType::Float64, &m);
+                let plain = Series::new("literal".into(), [2i32]);
+                let plain_field = Field::new("literal".into(), DataType::Int32);
+                // results of polars expressions, which the kernels must match
+                let unary = |expr: Expr, unit_tfms: fn(Units) -> Units| {
+                    let kernel = |value: &Series| Ok(polars_values(&[value], expr));
+                    laid_out(apply_unary(&input, kernel, Some(unit_tfms)))
+                };
+                let keep = |unit: Units| unit;
+                let lower = quantity(&[1.5], m.clone());
+                let cases = [
+                    ("mean", unary(col("value").mean(), keep), mean_output(&single)),
+                    ("median", unary(col("value").median(), keep), median_output(&single)),
+                    ("std", unary(col("value").std(1), keep), std_output(&single)),
+                    ("sum", unary(col("value").sum(), keep), sum_output(&single)),
+                    (
+                        "cum_sum",
+                        unary(col("value").cum_sum(false), keep),
+                        cum_sum_output(&single),
+                    ),
+                    ("abs", unary(col("value").abs(), keep), abs_output(&single)),
+                    ("sqrt", unary(col("value").sqrt(), |u| u.sqrt()), sqrt_output(&single)),
+                    ("round", unary(col("value").round(0), keep), round_output(&single)),
+                    (
+                        "sign",
+                        unary(col("value").sign(), |_| Units::dimensionless()),
+                        sign_output(&single),
+                    ),
+                    ("min", unary(col("value").min(), keep), min_output(&single)),
+                    (
+                        "pow_int",
+                        unary(col("value").pow(2), |u| u.pow_int(2)),
+                        pow_int_output(&single, PowIntKwarg { exp: 2 }),
+                    ),
+                    ("var", laid_out(var_quantity(&input, 1)), var_output(&single)),
+                    (
+                        "cum_prod",
+                        cum_prod_quantity(&input)
+                            .and_then(|result| to_row_product_layout(result, slice::from_ref(&input))),
+                        cum_prod_output(&single),
+                    ),
+                    (
+                        "product",
+                        product_quantity(&input)
+                            .and_then(|result| to_row_product_layout(result, slice::from_ref(&input))),
+                        product_output(&single),
+                    ),
+                    ("diff", laid_out(diff_quantity(&input, 1)), diff_output(&single)),
+                    (
+                        "pct_change",
+                        laid_out(pct_change_quantity(&input, 1)),
+                        pct_change_output(&single),
+                    ),
+                    (
+                        "pow_rat",
+                        laid_out(pow_rat_quantity(&input, Rational64::new(1, 3))),
+                        pow_rat_output(&single, PowRatKwarg { numer: 1, denom: 3 }),
+                    ),
+                    (
+                        "convert",
+                        laid_out(convert_quantity(&input, &Units::new_simple("cm"))),
+                        convert_output(&single, ConvertKwarg { to: "cm".into() }),
+                    ),
+                    (
+                        "exp",
+                        laid_out(apply_dimensionless(&ratio, "exp", f64::exp)),
+                        dimensionless_output(&[typed_field("value", &dtype, &ratio_unit)]),
+                    ),
+                    (
+                        "sin",
+                        laid_out(apply_trig(&angle, "sin", f64::sin)),
+                        trig_output(&[typed_field("value", &dtype, &degree)]),
+                    ),
+                    (
+                        "clip",
+                        laid_out(clip_quantity(&input, Some(&lower), None)),
+                        with_float_operands_output(&[field.clone(), bound.clone()]),
+                    ),
+                    (
+                        "shift",
+                        laid_out(shift_quantity(&input, 1, Some(&lower))),
+                        with_float_operands_output(&[field.clone(), bound.clone()]),
+                    ),
+                    (
+                        "add",
+                        laid_out(add_quantities(&input, &input)),
+                        add_output(&[field.clone(), field.clone()]),
+                    ),
+                    (
+                        "mul",
+                        laid_out(mul_quantities(&input, &input)),
+                        mul_output(&[field.clone(), field.clone()]),
+                    ),
+                    (
+                        "mul plain",
+                        laid_out(mul_quantities(&input, &plain)),
+                        mul_output(&[field.clone(), plain_field.clone()]),
+                    ),
+                    (
+                        "div",
+                        laid_out(div_quantities(&input, &input)),
+                        div_output(&[field.clone(), field.clone()]),
+                    ),
+                    (
+                        "dot",
+                        laid_out(dot_quantities(&input, &input)),
+                        dot_output(&[field.clone(), field.clone()]),
+                    ),
+                    (
+                        "km + km",
+                        laid_out(add_quantities(&distance, &distance)),
+                        add_output(&[distance_field.clone(), distance_field.clone()]),
+                    ),
+                    (
+                        "km + m",
+                        laid_out(add_quantities(&distance, &input)),
+                        add_output(&[distance_field.clone(), field.clone()]),
+                    ),
+                    (
+                        "m + km",
+                        laid_out(add_quantities(&input, &distance)),
+                        add_output(&[field.clone(), distance_field.clone()]),
+                    ),
+                    (
+                        "km - m",
+                        laid_out(sub_quantities(&distance, &input)),
+                        sub_output(&[distance_field.clone(), field.clone()]),
+                    ),
+                    (
+                        "degC - degC",
+                        laid_out(sub_quantities(&temperature, &temperature)),
+                        sub_output(&[temperature_field.clone(), temperature_field.clone()]),
+                    ),
+                    (
+                        "degC * m",
+                        laid_out(mul_quantities(&temperature, &input)),
+                        mul_output(&[temperature_field.clone(), field.clone()]),
+                    ),
+                    (
+                        "product degC",
+                        product_quantity(&temperature)
+                            .and_then(|result| to_row_product_layout(result, slice::from_ref(&temperature))),
+                        product_output(slice::from_ref(&temperature_field)),
+                    ),
+                    (
+                        "shift cm",
+                        laid_out(shift_quantity(&input, 1, Some(&fill))),
+                        with_float_operands_output(&[field.clone(), fill_field.clone()]),
+                    ),
+                    (
+                        "clip cm",
+                        laid_out(clip_quantity(&input, None, Some(&fill))),
+                        with_float_operands_output(&[field.clone(), fill_field.clone()]),
+                    ),
+                ];
+                for (name, result, output) in cases {
+                    let case = format!("{} of {} (typed: {})", name, dtype, typed);
+                    match result {
+                        Ok(result) => assert_eq!(result.dtype(), output.unwrap().dtype(), "{}", case),
+                        // integer values of quantities in the default layout are only converted by integer factors
+                        Err(error) => assert!(
+                            !typed && !dtype.is_float() && error.to_string().contains("cast them to floats first"),
+                            "{}: {}",
+                            case,
+                            error
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Values of a polars expression on columns named `value`, or `value_left` and `value_right`, the DataFrame round
+    /// trip which the kernels replace
+    fn polars_values(values: &[&Series], expr: Expr) -> Series {
+        let names = if values.len() == 1 {
+            vec!["value"]
+        } else {
+            vec!["value_left", "value_right"]
+        };
+        let columns = names
+            .into_iter()
+            .zip(values)
+            .map(|(name, value)| (*value).clone().with_name(name.into()).into_column())
+            .collect();
+        DataFrame::new(columns)
+            .unwrap()
+            .lazy()
+            .select([expr.alias("value")])
+            .collect()
+            .unwrap()
+            .column("value")
+            .unwrap()
+            .as_materialized_series()
+            .clone()
+    }
+
+    fn assert_same_values(result: &Series, expected: &Series, case: &str) {
+        assert_eq!(result.dtype(), expected.dtype(), "{}", case);
+        let as_floats = |s: &Series| s.cast(&DataType::Float64).unwrap().f64().unwrap().to_vec();
+        for (a, b) in as_floats(result).into_iter().zip(as_floats(expected)) {
+            let same = match (a, b) {
+                (Some(a), Some(b)) => a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= 1e-6 * a.abs().max(1.0),
+                (a, b) => a == b,
+            };
+            assert!(same, "{}: {:?} and {:?}", case, result, expected);
+        }
+        assert_eq!(result.len(), expected.len(), "{}", case);
+    }
+
+    #[test]
+    fn test_kernels_match_polars() {
+        let values = Series::new(
+            "value".into(),
+            [Some(4.0), None, Some(-1.5), Some(2.25), Some(0.0), Some(3.0)],
+        );
+        let exponents = Series::new("value".into(), [2.0, 0.5, 3.0, -1.0, 2.0, 0.0]);
+        for dtype in [DataType::Int32, DataType::Float32, DataType::Float64] {
+            let value = values.cast(&dtype).unwrap();
+            let fill = Series::new("fill".into(), [7]).cast(&dtype).unwrap();
+            let unary: Vec<(&str, PolarsResult<Series>, Expr)> = vec![
+                ("abs", polars::prelude::abs(&value), col("value").abs()),
+                ("neg", negate(&value), -col("value")),
+                ("ceil", value.ceil(), col("value").ceil()),
+                ("floor", value.floor(), col("value").floor()),
+                ("round", value.round(1), col("value").round(1)),
+                ("sign", sign_kernel(&value), col("value").sign()),
+                ("sqrt", float_kernel(&value, f64::sqrt), col("value").sqrt()),
+                ("cbrt", float_kernel(&value, f64::cbrt), col("value").cbrt()),
+                ("pow 0", pow_int_kernel(&value, 0), col("value").pow(0)),
+                ("pow 1", pow_int_kernel(&value, 1), col("value").pow(1)),
+                ("pow 3", pow_int_kernel(&value, 3), col("value").pow(3)),
+                ("pow 11", pow_int_kernel(&value, 11), col("value").pow(11)),
+                (
+                    "pow 1/3",
+                    float_kernel(&value, |v| v.powf(1.0 / 3.0)),
+                    col("value").pow(1.0 / 3.0),
+                ),
+                (
+                    "cum_sum",
+                    polars::prelude::cum_sum(&value, false),
+                    col("value").cum_sum(false),
+                ),
+                (
+                    "cum_prod",
+                    polars::prelude::cum_prod(&value, false),
+                    col("value").cum_prod(false),
+                ),
+                (
+                    "cum_min",
+                    polars::prelude::cum_min(&value, false),
+                    col("value").cum_min(false),
+                ),
+                (
+                    "cum_count",
+                    polars::prelude::cum_count(&value, false),
+                    col("value").cum_count(false),
+                ),
+                ("min", reduce(&value, |s| s.min_reduce()), col("value").min()),
+                ("max", reduce(&value, |s| s.max_reduce()), col("value").max()),
+                ("sum", reduce(&value, |s| s.sum_reduce()), col("value").sum()),
+                ("mean", reduce(&value, |s| Ok(s.mean_reduce())), col("value").mean()),
+                ("median", reduce(&value, |s| s.median_reduce()), col("value").median()),
+                ("std", reduce(&value, |s| s.std_reduce(1)), col("value").std(1)),
+                ("var", reduce(&value, |s| s.var_reduce(1)), col("value").var(1)),
+                ("product", reduce(&value, |s| s.product()), col("value").product()),
+                (
+                    "diff",
+                    polars::prelude::diff(&value, -1, NullBehavior::Ignore),
+                    col("value").diff(-1, NullBehavior::Ignore),
+                ),
+                (
+                    "pct_change",
+                    polars::prelude::pct_change(&value, &Series::new("n".into(), [1])),
+                    col("value").pct_change(lit(1)),
+                ),
+                ("shift", shift_kernel(&value, 2, None), col("value").shift(lit(2))),
+                (
+                    "shift fill",
+                    shift_kernel(&value, -2, Some(&fill)),
+                    col("value").shift_and_fill(lit(-2), lit(fill.clone())),
+                ),
+                (
+                    "arg_max",
+                    Ok(index_kernel(&value, value.arg_max())),
+                    col("value").arg_max(),
+                ),
+                (
+                    "arg_min",
+                    Ok(index_kernel(&value, value.arg_min())),
+                    col("value").arg_min(),
+                ),
+            ];
+            for (name, result, expr) in unary {
+                let case = format!("{} of {}", name, dtype);
+                assert_same_values(&result.unwrap(), &polars_values(&[&value], expr), &case);
+            }
+
+            let other = exponents.cast(&dtype).unwrap();
+            let (left, right) = (col("value_left"), col("value_right"));
+            let binary: Vec<(&str, PolarsResult<Series>, Expr)> = vec![
+                ("add", &value + &other, left.clone() + right.clone()),
+                ("mul", &value * &other, left.clone() * right.clone()),
+                ("div", &value / &other, left.clone() / right.clone()),
+                ("dot", dot_kernel(&value, &other), (left.clone() * right.clone()).sum()),
+                (
+                    "pow_column",
+                    pow_column_kernel(&value, &other),
+                    left.cast(DataType::Float64).pow(right),
+                ),
+            ];
+            for (name, result, expr) in binary {
+                let case = format!("{} of {}", name, dtype);
+                assert_same_values(&result.unwrap(), &polars_values(&[&value, &other], expr), &case);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pow_int_kernel_large_exponent() {
+        // 2^32 + 2 is 2 as an i32, the values are raised to the full exponent
+        let exp = (1_i64 << 32) + 2;
+        let values = Series::new("value".into(), [Some(1.0), None, Some(-1.0), Some(0.5), Some(2.0)]);
+        let result = pow_int_kernel(&values, exp).unwrap();
+        let expected = Series::new(
+            "value".into(),
+            [Some(1.0), None, Some(1.0), Some(0.0), Some(f64::INFINITY)],
+        );
+        assert_same_values(&result, &expected, "pow 2^32 + 2");
+    }
+
+    /// Kernels against the DataFrame round trip of the polars expressions they replace, on a quantity of 10M rows and
+    /// on the same rows in groups of 1000 as in `group_by().agg()`, with the default String unit of every row and with
+    /// a typed unit. Run with `cargo test --release bench_kernels -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_kernels() {
+        let len = 10_000_000;
+        let values: Vec<f64> = (0..len).map(|i| i as f64).collect();
+        for typed in [false, true] {
+            let layout = if typed { "typed" } else { "untyped" };
+            let input = with_layout(quantity(&values, Units::new_simple("m")), typed).unwrap();
+            let groups: Vec<Series> = (0..len).step_by(1000).map(|i| input.slice(i as i64, 1000)).collect();
+            let time = |f: &dyn Fn(&Series) -> Series| {
+                let start = std::time::Instant::now();
+                let _ = f(&input);
+                let whole = start.elapsed();
+                let start = std::time::Instant::now();
+                for group in &groups {
+                    let _ = f(group);
+                }
+                (whole, start.elapsed())
+            };
+            type Kernel = fn(&Series) -> PolarsResult<Series>;
+            let unary: [(&str, Kernel, Expr); 3] = [
+                ("abs", polars::prelude::abs, col("value").abs()),
+                (
+                    "cum_sum",
+                    |s| polars::prelude::cum_sum(s, false),
+                    col("value").cum_sum(false),
+                ),
+                ("mean", |s| reduce(s, |s| Ok(s.mean_reduce())), col("value").mean()),
+            ];
+            let keep = None::<fn(Units) -> Units>;
+            for (name, kernel, expr) in unary {
+                let kernels = time(&|s| apply_unary(s, kernel, keep).unwrap());
+                let round_trip =
+                    time(&|s| apply_unary(s, |value| Ok(polars_values(&[value], expr.clone())), keep).unwrap());
+                println!("{layout} {name}: kernel {kernels:?}, round trip {round_trip:?} (10M rows, groups of 1000)");
+            }
+            let kernels = time(&|s| apply_binary(s, s, |l, r| l + r, None).unwrap());
+            let round_trip = time(&|s| {
+                let expr = col("value_left") + col("value_right");
+                apply_binary(s, s, |l, r| Ok(polars_values(&[l, r], expr)), None).unwrap()
+            });
+            println!("{layout} add: kernel {kernels:?}, round trip {round_trip:?} (10M rows, groups of 1000)");
+        }
+    }
+
+    fn typed_field(unit: &str) -> Field {
+        quantity_field("value".into(), DataType::Float64, Some(unit.parse().unwrap())).unwrap()
+    }
+
+    fn field_units(field: PolarsResult<Field>) -> Units {
+        typed_field_units(&field.unwrap()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_planned_units() {
+        let (m, km, s) = (typed_field("m"), typed_field("km"), typed_field("s"));
+        assert_eq!(
+            field_units(add_output(&[km.clone(), m.clone()])),
+            Units::new_simple("km")
+        );
+        assert_eq!(field_units(div_output(&[m.clone(), s.clone()])), "m/s".parse().unwrap());
+        let error = add_output(&[m.clone(), s.clone()]).unwrap_err().to_string();
+        assert!(error.contains("while converting s to m"), "{}", error);
+        assert!(compare_output(&[m.clone(), s.clone()]).is_err());
+        assert!(trig_output(slice::from_ref(&m)).is_err());
+        assert!(convert_output(slice::from_ref(&m), ConvertKwarg { to: "s".into() }).is_err());
+        assert!(pow_column_output(&[m.clone(), typed_field("")]).is_err());
+        // plain numbers are dimensionless
+        let plain = Field::new("literal".into(), DataType::Int32);
+        assert!(add_output(&[m.clone(), plain.clone()]).is_err());
+        assert_eq!(field_units(mul_output(&[m.clone(), plain])), Units::new_simple("m"));
+
+        // pint's rules for offset units
+        let (deg_c, delta) = (typed_field("degC"), typed_field("delta_degF"));
+        assert!(add_output(&[deg_c.clone(), deg_c.clone()]).is_err());
+        assert_eq!(
+            field_units(add_output(&[delta.clone(), deg_c.clone()])),
+            Units::new_simple("degC")
+        );
+        assert_eq!(
+            field_units(sub_output(&[deg_c.clone(), deg_c.clone()])),
+            Units::new_simple("delta_degC")
+        );
+        assert!(sub_output(&[delta, deg_c.clone()]).is_err());
+        assert_eq!(field_units(var_output(&[deg_c])), "delta_degC^2".parse().unwrap());
+
+        // units are only checked when all of them are known
+        let untyped = quantity_field("value".into(), DataType::Float64, None).unwrap();
+        let output = add_output(&[untyped, s]).unwrap();
+        assert!(typed_field_units(&output).is_none());
+    }
+
+    #[test]
+    fn test_typed_layout() {
+        let km = with_layout(quantity(&[1.0, 2.0], Units::new_simple("km")), true).unwrap();
+        let m = with_layout(quantity(&[500.0, 500.0], Units::new_simple("m")), true).unwrap();
+        assert!(is_typed(&km));
+        assert_eq!(first_unit(&km).unwrap(), Units::new_simple("km"));
+        let inputs = [km.clone(), m.clone()];
+        let result = to_input_layout(add_quantities(&km, &m).unwrap(), &inputs).unwrap();
+        assert!(is_close::all_close!(values(&result), [1.5, 2.5]));
+        assert_eq!(
+            result.dtype(),
+            add_output(&[km.field().into_owned(), m.field().into_owned()])
+                .unwrap()
+                .dtype()
+        );
+        // a result is typed only when all of its inputs are
+        let untyped = quantity(&[1.0, 2.0], Units::new_simple("m"));
+        let result = to_input_layout(add_quantities(&km, &untyped).unwrap(), &[km.clone(), untyped.clone()]).unwrap();
+        assert!(!is_typed(&result));
+        assert_eq!(
+            with_layout(with_layout(untyped.clone(), true).unwrap(), false).unwrap(),
+            untyped
+        );
+        let ratio = with_layout(quantity(&[1.0], Units::dimensionless()), true).unwrap();
+        assert_eq!(first_unit(&ratio).unwrap(), Units::dimensionless());
+    }
+
+    /// A quantity with a different unit per row
+    fn quantity_per_row(values: &[f64], units: &[&str]) -> Series {
+        let fields = [Series::new("value".into(), values), Series::new("unit".into(), units)];
+        StructChunked::from_series("value".into(), values.len(), fields.iter())
+            .unwrap()
+            .into_series()
+    }
+
+    /// A quantity with a different unit per row, in the list layout
+    fn list_quantity_per_row(values: &[f64], units: &[&str]) -> Series {
+        let unit_rows: Vec<Series> = units
+            .iter()
+            .map(
+                |unit| match unit.parse::<Units>().unwrap().to_list_scalar().unwrap().value() {
+                    AnyValue::List(row) => row.clone(),
+                    _ => unreachable!(),
+                },
+            )
+            .collect();
+        let fields = [
+            Series::new("value".into(), values),
+            Series::new("unit".into(), unit_rows),
+        ];
+        StructChunked::from_series("value".into(), values.len(), fields.iter())
+            .unwrap()
+            .into_series()
+    }
+
+    #[test]
+    fn test_per_row_units() {
+        type PerRow = fn(&[f64], &[&str]) -> Series;
+        for quantity_per_row in [quantity_per_row as PerRow, list_quantity_per_row] {
+            let input = quantity_per_row(&[1.0, 500.0, 1.0, 2.0], &["kg", "g", "lb", "kg"]);
+            let (value, unit) = extract_quantity(&input).unwrap();
+            let value: Vec<f64> = value.f64().unwrap().into_no_null_iter().collect();
+            assert!(is_close::all_close!(value, [1.0, 0.5, 0.45359237, 2.0]));
+            assert_eq!(Units::from_scalar(unit.first()).unwrap(), Units::new_simple("kg"));
+
+            let input = quantity_per_row(&[0.0, 273.15], &["degC", "K"]);
+            let converted = convert_quantity(&input, &"K".parse().unwrap()).unwrap();
+            assert!(is_close::all_close!(values(&converted), [273.15, 273.15]));
+
+            let input = quantity_per_row(&[1.0, 1.0], &["kg", "m"]);
+            let error = extract_quantity(&input).unwrap_err().to_string();
+            assert!(error.contains("Expected all units to be compatible"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_integer_values_in_other_units() {
+        let integer_quantity = |values: &[i64], units: &[&str]| {
+            let fields = [Series::new("value".into(), values), Series::new("unit".into(), units)];
+            StructChunked::from_series("value".into(), values.len(), fields.iter())
+                .unwrap()
+                .into_series()
+        };
+        let integers = |s: &Series| -> Vec<i64> {
+            let (value, _) = extract_quantity(s).unwrap();
+            value.i64().unwrap().into_no_null_iter().collect()
+        };
+        // integer values stay integers when they are converted by integer factors
+        assert_eq!(integers(&integer_quantity(&[1, 2], &["cm", "m"])), [1, 200]);
+        let (km, m) = (
+            integer_quantity(&[1, 2], &["km", "km"]),
+            integer_quantity(&[500, 1], &["m", "m"]),
+        );
+        assert_eq!(integers(&add_quantities(&km, &km).unwrap()), [2, 4]);
+        assert_eq!(integers(&add_quantities(&m, &km).unwrap()), [1500, 2001]);
+        // other factors would truncate them
+        let errors = [
+            extract_quantity(&integer_quantity(&[1, 2], &["m", "cm"])).unwrap_err(),
+            add_quantities(&km, &m).unwrap_err(),
+            mul_quantities(&integer_quantity(&[20, 25], &["degC", "degC"]), &m).unwrap_err(),
+        ];
+        for error in errors {
+            assert!(error.to_string().contains("cast them to floats first"), "{}", error);
+        }
+        // conversions to floats and comparisons take any factor
+        let converted = convert_quantity(&m, &Units::new_simple("km")).unwrap();
+        assert!(is_close::all_close!(values(&converted), [0.5, 0.001]));
+        let (left, right) = values_in_left_unit(&km, &m).unwrap();
+        assert_eq!(
+            booleans(&left.gt(&right).unwrap().into_series()),
+            [Some(true), Some(true)]
+        );
+
+        // Float32 values stay Float32
+        let fields = [
+            Series::new("value".into(), [1.0f32, 2.0]),
+            Series::new("unit".into(), ["m", "cm"]),
+        ];
+        let input = StructChunked::from_series("value".into(), 2, fields.iter())
+            .unwrap()
+            .into_series();
+        let (value, _) = extract_quantity(&input).unwrap();
+        assert_eq!(value.dtype(), &DataType::Float32);
+        assert_eq!(value.f32().unwrap().get(1), Some(0.02));
+    }
+
+    #[test]
+    fn test_list_layout() {
+        let compact = quantity_per_row(&[1.0, 2.0, 300.0], &["m*s", "s*m", "cm*s"]);
+        let list = list_quantity_per_row(&[1.0, 2.0, 300.0], &["m*s", "s*m", "cm*s"]);
+        assert_eq!(
+            compact.struct_().unwrap().fields_as_series()[1].dtype(),
+            &Units::dtype()
+        );
+
+        let compact_sum = apply_binary(&compact, &compact, |l, r| l + r, None).unwrap();
+        let list_sum = apply_binary(&list, &compact, |l, r| l + r, None).unwrap();
+        assert_eq!(values(&compact_sum), [2.0, 4.0, 6.0]);
+        assert_eq!(values(&list_sum), values(&compact_sum));
+        // results are in the compact layout, whatever the layout of the inputs
+        let list_sum = to_input_layout(list_sum, &[list]).unwrap();
+        assert!(list_sum.equals(&to_input_layout(compact_sum, &[compact]).unwrap()));
+        let unit = list_sum.struct_().unwrap().fields_as_series()[1].clone();
+        assert_eq!(unit.str().unwrap().get(2), Some("m*s"));
+    }
+
+    fn values(s: &Series) -> Vec<f64> {
+        extract_quantity(s)
+            .unwrap()
+            .0
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_sub_offset_units() {
+        let left = quantity(&[20.0, 30.0], "degC".parse().unwrap());
+        let right = quantity(&[50.0, 68.0], "degF".parse().unwrap());
+        let result = sub_quantities(&left, &right).unwrap();
+        assert!(is_close::all_close!(values(&result), [10.0, 10.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("delta_degC"));
+        // a temperature minus a difference is a temperature
+        let diff = quantity(&[18.0, 18.0], "delta_degF".parse().unwrap());
+        let result = sub_quantities(&left, &diff).unwrap();
+        assert!(is_close::all_close!(values(&result), [10.0, 20.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("degC"));
+        assert!(sub_quantities(&diff, &left).is_err());
+    }
+
+    #[test]
+    fn test_add_offset_units() {
+        let temp = quantity(&[20.0], "degC".parse().unwrap());
+        let diff = quantity(&[9.0], "delta_degF".parse().unwrap());
+        let result = add_quantities(&diff, &temp).unwrap();
+        assert!(is_close::all_close!(values(&result), [25.0]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("degC"));
+        assert!(add_quantities(&temp, &temp).is_err());
+        // kelvin is an absolute temperature, not a difference
+        assert!(add_quantities(&temp, &quantity(&[1.0], Units::new_simple("K"))).is_err());
+    }
+
+    #[test]
+    fn test_to_absolute() {
+        let temp = quantity(&[0.0, 100.0], "degC".parse().unwrap());
+        let result = to_absolute(&temp).unwrap();
+        assert!(is_close::all_close!(values(&result), [273.15, 373.15]));
+        assert_eq!(first_unit(&result).unwrap(), Units::new_simple("kelvin"));
+        let length = quantity(&[1.0], Units::new_simple("m"));
+        assert_eq!(to_absolute(&length).unwrap(), length);
+    }
+
+    #[test]
+    fn test_convert_incompatible_units() {
+        let input = quantity(&[1.0], "m/s".parse().unwrap());
+        let result = convert_quantity(&input, &"km".parse().unwrap());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_add_different_units() {
+        let left = quantity(&[1.0], Units::new_simple("m"));
+        let right = quantity(&[1.0], Units::new_simple("s"));
+        let err = apply_binary(&left, &right, |l, r| l + r, None).unwrap_err();
+        assert!(err.to_string().contains("Expected units to be the same, got m and s"));
     }
 }
@@ -1,12 +1,16 @@
 mod expressions;
+mod registry;
 mod units;
 use pyo3::types::{PyModule, PyModuleMethods};
-use pyo3::{pymodule, Bound, PyResult};
+use pyo3::{pymodule, wrap_pyfunction, Bound, PyResult};
 use pyo3_polars::PolarsAllocator;
 
 #[pymodule]
 fn _internal(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(registry::define_dimension, m)?)?;
+    m.add_function(wrap_pyfunction!(registry::define_unit, m)?)?;
+    m.add_function(wrap_pyfunction!(registry::load_definitions, m)?)?;
     Ok(())
 }
 
@@ -0,0 +1,58 @@
+//! Python bindings to define units at runtime in the global registry
+#![allow(clippy::useless_conversion)] // false positive on the code generated by pyfunction
+
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyfunction, PyErr, PyResult};
+
+use crate::units::definitions::registry_mut;
+use crate::units::UnitRegistry;
+
+fn to_py_err(error: anyhow::Error) -> PyErr {
+    PyValueError::new_err(format!("{:#}", error))
+}
+
+/// Define a base dimension, or a derived one when `definition` is given (e.g. `[length] / [time]`). An existing
+/// dimension is only redefined with `replace=True`
+#[pyfunction]
+#[pyo3(signature = (name, definition=None, replace=false))]
+pub fn define_dimension(name: &str, definition: Option<&str>, replace: bool) -> PyResult<()> {
+    registry_mut()
+        .define_dimension(name, definition, replace)
+        .map_err(to_py_err)
+}
+
+/// Define a unit as `value * factor + offset` in `base_unit`, or as the base unit of `dimension`. An existing unit is
+/// only redefined with `replace=True`
+#[pyfunction]
+#[pyo3(signature = (name, dimension=None, factor=1.0, base_unit=None, offset=None, aliases=vec![], replace=false))]
+pub fn define_unit(
+    name: &str,
+    dimension: Option<&str>,
+    factor: f64,
+    base_unit: Option<&str>,
+    offset: Option<f64>,
+    aliases: Vec<String>,
+    replace: bool,
+) -> PyResult<()> {
+    let aliases: Vec<&str> = aliases.iter().map(String::as_str).collect();
+    registry_mut()
+        .define_unit(name, dimension, factor, base_unit, offset, &aliases, replace)
+        .map_err(to_py_err)
+}
+
+/// Load definitions in pint's format, either all the definitions are loaded or none. Existing units, prefixes and
+/// dimensions are only redefined with `replace=True`. With `from_scratch=True` the definitions replace the built-in
+/// units instead of extending them, e.g. to load pint's `default_en.txt`
+#[pyfunction]
+#[pyo3(signature = (text, replace=false, from_scratch=false))]
+pub fn load_definitions(text: &str, replace: bool, from_scratch: bool) -> PyResult<()> {
+    let mut registry = registry_mut();
+    let mut new_registry = if from_scratch {
+        UnitRegistry::new()
+    } else {
+        registry.clone()
+    };
+    new_registry.load_definitions(text, replace).map_err(to_py_err)?;
+    *registry = new_registry;
+    Ok(())
+}
@@ -1,22 +1,53 @@
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
 use num_rational::Rational64;
-use num_traits::FromPrimitive;
 use polars::prelude::*;
 
 mod conversion;
 pub mod definitions;
+mod definitions_file;
+mod format;
+mod parser;
+
+pub use conversion::UnitRegistry;
+pub use format::UnitFormat;
+pub use parser::parse_unit_expr;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Unit {
     pub name: std::string::String,
     pub power: Rational64,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A product of units raised to a (rational) power.
+///
+/// Equality and hashing are semantic, i.e. they are based on the canonical form of the units (`m*s == s*m`)
+#[derive(Debug, Clone)]
 pub struct Units {
     pub units: Vec<Unit>,
 }
 
+impl PartialEq for Units {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical().units == other.canonical().units
+    }
+}
+
+impl Eq for Units {}
+
+impl Hash for Units {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical().units.hash(state);
+    }
+}
+
 impl Units {
+    /// No units, e.g. the unit of `m/m` or of the sign of a quantity
+    pub fn dimensionless() -> Self {
+        Units { units: vec![] }
+    }
+
     pub fn new_simple(name: &str) -> Self {
         Units {
             units: vec![Unit {
@@ -29,6 +60,12 @@ impl Units {
         if scalar.is_null() {
             polars_bail!(ComputeError: "Unit is Null");
         }
+        if let Some(units) = Self::from_typed_dtype(scalar.dtype()) {
+            return units;
+        }
+        if let Some(unit) = scalar.value().get_str() {
+            return unit.parse();
+        }
         if let AnyValue::List(list) = scalar.value() {
             let ca_units: &StructChunked = list.struct_()?;
             if ca_units.has_nulls() {
@@ -50,11 +87,54 @@ impl Units {
                 .collect::<Vec<Unit>>();
             Ok(Self { units })
         } else {
-            polars_bail!(ComputeError: "Expected List of Structs, got {:?}", scalar.dtype());
+            polars_bail!(ComputeError: "Expected String or List of Structs, got {:?}", scalar.dtype());
+        }
+    }
+
+    /// The polars dtype used to store a `Units` value: its canonical string (e.g. `kg*m/s^2`) in every row
+    pub fn dtype() -> DataType {
+        DataType::String
+    }
+
+    /// The dtype of the list layout, where every row stores the units as a list of `{name, power}` structs. It is
+    /// still accepted as input but no longer produced, as it needs much more memory than the canonical string
+    #[cfg(test)]
+    pub fn list_dtype() -> DataType {
+        DataType::List(Box::new(DataType::Struct(vec![
+            Field::new("name".into(), DataType::String),
+            Field::new(
+                "power".into(),
+                DataType::Struct(vec![
+                    Field::new("numer".into(), DataType::Int64),
+                    Field::new("denom".into(), DataType::Int64),
+                ]),
+            ),
+        ])))
+    }
+
+    /// The dtype of the typed layout, where the unit is stored in the dtype instead of in every row: an `Enum` whose
+    /// only category is the unit (e.g. `m/s^2`), so that units are known when a query is planned
+    pub fn typed_dtype(&self) -> PolarsResult<DataType> {
+        let categories = Series::new("unit".into(), [self.canonical().to_string()]);
+        let categories = categories.str()?.downcast_iter().next().expect("one chunk").clone();
+        Ok(create_enum_dtype(categories))
+    }
+
+    /// The units of a typed layout dtype (see `typed_dtype`), `None` for other dtypes
+    pub fn from_typed_dtype(dtype: &DataType) -> Option<PolarsResult<Self>> {
+        match dtype {
+            DataType::Enum(Some(rev_map), _) if rev_map.len() == 1 => Some(rev_map.get(0).parse()),
+            _ => None,
         }
     }
 
     pub fn check_valid_unit_dtype(dtype: &DataType) -> PolarsResult<()> {
+        if let Some(units) = Self::from_typed_dtype(dtype) {
+            return units.map(|_| ());
+        }
+        if let DataType::String = dtype {
+            return Ok(());
+        }
         if let DataType::List(inner) = dtype {
             if let DataType::Struct(fields) = inner.as_ref() {
                 Self::check_unit_fields(fields)
@@ -62,7 +142,7 @@ impl Units {
                 polars_bail!(ComputeError: "Invalid Unit dtype: expected List of Struct, got List of {:?}", inner)
             }
         } else {
-            polars_bail!(ComputeError: "Invalid Unit dtype: expected List, got {:?}", dtype)
+            polars_bail!(ComputeError: "Invalid Unit dtype: expected String or List, got {:?}", dtype)
         }
     }
 
@@ -86,13 +166,24 @@ impl Units {
         }
         polars_bail!(ComputeError: "Invalid Unit struct. Expected fields 'name' and 'power' with types String and Int64, Int64, got {:?}", fields);
     }
+    /// The units as a scalar of the default layout (see `dtype`)
     pub fn to_scalar(&self) -> PolarsResult<Scalar> {
-        let names: Series = self.units.iter().map(|u| u.name.clone()).collect();
-        let numers: Series = self.units.iter().map(|u| Some(*u.power.numer())).collect();
-        let denoms: Series = self.units.iter().map(|u| Some(*u.power.denom())).collect();
+        Ok(Scalar::new(
+            Self::dtype(),
+            AnyValue::StringOwned(self.canonical().to_string().into()),
+        ))
+    }
+
+    /// The units as a scalar of the list layout (see `list_dtype`)
+    #[cfg(test)]
+    pub fn to_list_scalar(&self) -> PolarsResult<Scalar> {
+        let canonical = self.canonical();
+        let names: Series = canonical.units.iter().map(|u| u.name.clone()).collect();
+        let numers: Series = canonical.units.iter().map(|u| Some(*u.power.numer())).collect();
+        let denoms: Series = canonical.units.iter().map(|u| Some(*u.power.denom())).collect();
         let powers = StructChunked::from_series(
             "power".into(),
-            self.units.len(),
+            canonical.units.len(),
             [numers.with_name("numer".into()), denoms.with_name("denom".into())].iter(),
         )?
         .into_series();
@@ -101,19 +192,25 @@ impl Units {
             names.len(),
             [names.with_name("name".into()), powers.with_name("power".into())].iter(),
         )?;
-        Ok(Scalar::new(
-            DataType::List(Box::new(DataType::Struct(vec![
-                Field::new("name".into(), DataType::String),
-                Field::new(
-                    "power".into(),
-                    DataType::Struct(vec![
-                        Field::new("numer".into(), DataType::Int64),
-                        Field::new("denom".into(), DataType::Int64),
-                    ]),
-                ),
-            ]))),
-            AnyValue::List(ca_struct.into_series()),
-        ))
+        Ok(Scalar::new(Self::list_dtype(), AnyValue::List(ca_struct.into_series())))
+    }
+
+    /// Canonical form of the units: duplicates merged, powers reduced, zero powers dropped and sorted by name
+    pub fn canonical(&self) -> Self {
+        let mut units: Vec<Unit> = Vec::with_capacity(self.units.len());
+        for unit in &self.units {
+            if let Some(u) = units.iter_mut().find(|u| u.name == unit.name) {
+                u.power += unit.power;
+            } else {
+                units.push(unit.clone());
+            }
+        }
+        units.retain(|u| *u.power.numer() != 0);
+        for unit in &mut units {
+            unit.power = unit.power.reduced();
+        }
+        units.sort_by(|a, b| a.name.cmp(&b.name));
+        Self { units }
     }
 
     pub fn multiply(&self, other: &Self) -> Self {
@@ -125,7 +222,7 @@ impl Units {
                 units.push(unit_other.clone());
             }
         }
-        Self { units }
+        Self { units }.canonical()
     }
 
     pub fn divide(&self, other: &Self) -> Self {
@@ -140,7 +237,7 @@ impl Units {
                 });
             }
         }
-        Self { units }
+        Self { units }.canonical()
     }
 
     pub fn pow_int(&self, n: i64) -> Self {
@@ -152,19 +249,7 @@ impl Units {
                 power: (u.power * n).reduced(),
             })
             .collect();
-        Self { units }
-    }
-
-    pub fn pow_float(&self, n: f64) -> Self {
-        let units = self
-            .units
-            .iter()
-            .map(|u| Unit {
-                name: u.name.clone(),
-                power: u.power * Rational64::from_f64(n).unwrap(),
-            })
-            .collect();
-        Self { units }
+        Self { units }.canonical()
     }
 
     pub fn pow_rat(&self, n: Rational64) -> Self {
@@ -176,7 +261,7 @@ impl Units {
                 power: u.power * n,
             })
             .collect();
-        Self { units }
+        Self { units }.canonical()
     }
 
     pub fn sqrt(&self) -> Self {
@@ -184,6 +269,57 @@ impl Units {
     }
 }
 
+impl FromStr for Units {
+    type Err = PolarsError;
+
+    /// Parse a unit expression like `kg*m/s^2`, `s^(1/2)` or `meter / second ** 2`
+    fn from_str(s: &str) -> PolarsResult<Self> {
+        let expr = parse_unit_expr(s)?;
+        if expr.factor != 1.0 {
+            polars_bail!(ComputeError: "Unit expression '{}' has a numeric scale factor {}, which cannot be represented as a unit", s, expr.factor);
+        }
+        Ok(expr.units)
+    }
+}
+
+/// Closest rational to `x` with a denominator of at most `max_denom` (like python's `Fraction.limit_denominator`)
+///
+/// Returns `None` for NaN or infinite values.
+pub fn rational_from_f64(x: f64, max_denom: i64) -> Option<Rational64> {
+    if !x.is_finite() || max_denom < 1 || x.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    let sign = if x < 0.0 { -1 } else { 1 };
+    let x = x.abs();
+    // continued fraction expansion, (p0/q0, p1/q1) are the last two convergents
+    let (mut p0, mut q0, mut p1, mut q1) = (0i64, 1i64, 1i64, 0i64);
+    let mut rem = x;
+    loop {
+        let a = rem.floor() as i64;
+        let q2 = match a.checked_mul(q1).and_then(|aq| aq.checked_add(q0)) {
+            Some(q2) if q2 <= max_denom => q2,
+            _ => break,
+        };
+        let p2 = p0.checked_add(a.checked_mul(p1)?)?;
+        (p0, q0, p1, q1) = (p1, q1, p2, q2);
+        let frac = rem - a as f64;
+        if frac <= f64::EPSILON * x.max(1.0) || (x - p1 as f64 / q1 as f64).abs() <= f64::EPSILON * x.max(1.0) {
+            return Some(Rational64::new(sign * p1, q1));
+        }
+        rem = 1.0 / frac;
+    }
+    // the best approximation is either the last convergent or the semiconvergent bound
+    let k = (max_denom - q0) / q1;
+    let bound2 = Rational64::new(p1, q1);
+    let bound1 = match (k.checked_mul(p1).and_then(|kp| kp.checked_add(p0)), q0 + k * q1) {
+        (Some(numer), denom) => Rational64::new(numer, denom),
+        (None, _) => bound2,
+    };
+    let dist = |r: Rational64| (*r.numer() as f64 / *r.denom() as f64 - x).abs();
+    let best = if dist(bound2) <= dist(bound1) { bound2 } else { bound1 };
+    Some(best * sign)
+}
+
 #[cfg(test)]
 mod test {
     use num_rational::Rational64;
@@ -247,6 +383,22 @@ mod test {
         assert_eq!(units.units.len(), 0);
     }
 
+    #[test]
+    fn test_scalar_layouts() {
+        let units: Units = "m*s^2".parse().unwrap();
+        let scalar = units.to_scalar().unwrap();
+        assert_eq!(scalar.dtype(), &DataType::String);
+        assert_eq!(scalar.value().get_str(), Some("m*s^2"));
+        let list_scalar = units.to_list_scalar().unwrap();
+        assert_eq!(list_scalar.dtype(), &Units::list_dtype());
+        assert_eq!(
+            Units::from_scalar(list_scalar).unwrap(),
+            Units::from_scalar(scalar).unwrap()
+        );
+        assert!(Units::check_valid_unit_dtype(&Units::dtype()).is_ok());
+        assert!(Units::check_valid_unit_dtype(&Units::list_dtype()).is_ok());
+    }
+
     #[test]
     fn test_invalid_scalar() {
         let invalid_scalar = Scalar::new(DataType::Int32, AnyValue::Int32(42));
@@ -288,6 +440,105 @@ mod test {
         assert_eq!(units.units[1].power, Rational64::new(5, 1));
     }
 
+    #[test]
+    fn test_canonical() {
+        let units: Units = "s*m^2/m^2*s^(2/4)*kg".parse().unwrap();
+        let canonical = units.canonical();
+        assert_eq!(
+            canonical.units,
+            vec![
+                Unit {
+                    name: "kg".to_string(),
+                    power: Rational64::new(1, 1),
+                },
+                Unit {
+                    name: "s".to_string(),
+                    power: Rational64::new(3, 2),
+                },
+            ]
+        );
+        let duplicated = Units {
+            units: vec![
+                Unit {
+                    name: "m".to_string(),
+                    power: Rational64::new(1, 1),
+                },
+                Unit {
+                    name: "m".to_string(),
+                    power: Rational64::new(-1, 1),
+                },
+            ],
+        };
+        assert!(duplicated.canonical().units.is_empty());
+    }
+
+    #[test]
+    fn test_semantic_eq_and_hash() {
+        use std::collections::HashSet;
+
+        let ms: Units = "m*s".parse().unwrap();
+        let sm = Units {
+            units: vec![
+                Unit {
+                    name: "s".to_string(),
+                    power: Rational64::new(2, 2),
+                },
+                Unit {
+                    name: "m".to_string(),
+                    power: Rational64::new(1, 1),
+                },
+                Unit {
+                    name: "kg".to_string(),
+                    power: Rational64::new(0, 1),
+                },
+            ],
+        };
+        assert_eq!(ms, sm);
+        assert_ne!(ms, "m/s".parse().unwrap());
+        let set: HashSet<Units> = [ms, sm].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_divide_drops_zero_powers() {
+        let m = Units::new_simple("m");
+        assert!(m.divide(&m).units.is_empty());
+        assert!(m.pow_int(0).units.is_empty());
+    }
+
+    #[test]
+    fn test_typed_dtype() {
+        let units: Units = "kg*m/s^2".parse().unwrap();
+        let dtype = units.typed_dtype().unwrap();
+        assert!(matches!(dtype, DataType::Enum(_, _)));
+        assert_eq!(Units::from_typed_dtype(&dtype).unwrap().unwrap(), units);
+        // the same units in a different order have the same dtype
+        assert_eq!("m*kg/s^2".parse::<Units>().unwrap().typed_dtype().unwrap(), dtype);
+        assert!(Units::check_valid_unit_dtype(&dtype).is_ok());
+
+        let unit_col = Series::new("unit".into(), ["kg*m/s^2"]).cast(&dtype).unwrap();
+        assert_eq!(
+            Units::from_scalar(Scalar::new(dtype, unit_col.get(0).unwrap().into_static())).unwrap(),
+            units
+        );
+        assert!(Units::from_typed_dtype(&Units::dtype()).is_none());
+    }
+
+    #[test]
+    fn test_rational_from_f64() {
+        assert_eq!(rational_from_f64(0.5, 100), Some(Rational64::new(1, 2)));
+        assert_eq!(rational_from_f64(0.1, 100), Some(Rational64::new(1, 10)));
+        assert_eq!(rational_from_f64(-0.75, 100), Some(Rational64::new(-3, 4)));
+        assert_eq!(rational_from_f64(1.0 / 3.0, 1_000_000), Some(Rational64::new(1, 3)));
+        assert_eq!(rational_from_f64(3.0, 1), Some(Rational64::from_integer(3)));
+        assert_eq!(
+            rational_from_f64(std::f64::consts::PI, 100),
+            Some(Rational64::new(311, 99))
+        );
+        assert_eq!(rational_from_f64(f64::NAN, 100), None);
+        assert_eq!(rational_from_f64(f64::INFINITY, 100), None);
+    }
+
     #[test]
     fn test_divide() {
         let units1 = Units {
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 use std::ops::{Div, Mul};
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{bail, Context, Result};
 use num_rational::Rational64;
 
+use super::Units;
+
 // Other option
 
 // we can express the relation between units (and dimensions) either as a nested graph of operations or as a tree of operations.
@@ -46,7 +48,6 @@ pub struct Unit {
 }
 
 impl Conversion {
-    #[allow(dead_code)]
     pub fn new(factor: f64, unit: SimpleUnit) -> Self {
         Self {
             factor,
@@ -84,19 +85,46 @@ impl Dimension {
         }
     }
     pub fn pow(&self, exp: i64) -> Self {
+        self.pow_rat(Rational64::from_integer(exp))
+    }
+
+    pub fn pow_rat(&self, exp: Rational64) -> Self {
         let mut new_dimensions = self.dimensions.clone();
         for dim in &mut new_dimensions {
-            dim.1 *= Rational64::from_integer(exp);
+            dim.1 *= exp;
         }
-        Self {
-            name: format!("{}^{}", self.name, exp),
+        let mut dim = Self {
+            name: pow_name(&self.name, exp),
             dimensions: new_dimensions,
-        }
+        };
+        dim.simplify();
+        dim
     }
 
     pub fn simplify(&mut self) {
         self.dimensions.retain(|(_, power)| *power.numer() != 0);
     }
+
+    /// Dimensions sorted by name, so that they can be compared regardless of the order they were combined in
+    fn sorted_dimensions(&self) -> Vec<(String, Rational64)> {
+        let mut dimensions = self.dimensions.clone();
+        dimensions.sort_by(|a, b| a.0.cmp(&b.0));
+        dimensions
+    }
+
+    /// Whether two dimensions have the same base dimensions (e.g. `[velocity]` and `[length]/[time]`)
+    pub fn is_compatible(&self, other: &Dimension) -> bool {
+        self.sorted_dimensions() == other.sorted_dimensions()
+    }
+}
+
+/// `name^exp`, with parenthesis around fractional exponents
+fn pow_name(name: &str, exp: Rational64) -> String {
+    if exp.is_integer() {
+        format!("{}^{}", name, exp)
+    } else {
+        format!("{}^({})", name, exp)
+    }
 }
 impl Mul for Dimension {
     type Output = Dimension;
@@ -146,10 +174,10 @@ impl SimpleUnit {
     fn simplify(&mut self) {
         self.dimension.simplify();
     }
-    pub fn pow(&self, exp: i64) -> Self {
+    pub fn pow_rat(&self, exp: Rational64) -> Self {
         SimpleUnit {
-            name: format!("{}^{}", self.name, exp),
-            dimension: self.dimension.pow(exp),
+            name: pow_name(&self.name, exp),
+            dimension: self.dimension.pow_rat(exp),
         }
     }
 }
@@ -180,10 +208,12 @@ impl Div for SimpleUnit {
     }
 }
 
+/// Products of units fail instead of panicking when a unit doesn't have the dimensions of the base unit of its
+/// conversion, as units can be defined at runtime
 impl Mul for Unit {
-    type Output = Unit;
+    type Output = Result<Unit>;
 
-    fn mul(self, rhs: Unit) -> Unit {
+    fn mul(self, rhs: Unit) -> Result<Unit> {
         let conversion = match (self.conversion, rhs.conversion) {
             (Some(conv1), Some(conv2)) => Some(Conversion {
                 factor: conv1.factor * conv2.factor,
@@ -202,25 +232,14 @@ impl Mul for Unit {
             }),
             (None, None) => None,
         };
-        let simple_unit = self.simple_unit * rhs.simple_unit;
-        if let Some(ref conversion) = conversion {
-            assert_eq!(
-                simple_unit.dimension.dimensions, conversion.base_unit.dimension.dimensions,
-                "after multiplication the dimensions are not the same"
-            );
-        }
-        Unit {
-            simple_unit,
-            conversion,
-        }
+        Unit::new(self.simple_unit * rhs.simple_unit, conversion)
     }
 }
 
 impl Div for Unit {
-    type Output = Unit;
+    type Output = Result<Unit>;
 
-    fn div(self, rhs: Unit) -> Unit {
-        // println!("self {:?}, rhs {:?}", self, rhs);
+    fn div(self, rhs: Unit) -> Result<Unit> {
         let conversion = match (self.conversion, rhs.conversion) {
             (Some(conv1), Some(conv2)) => Some(Conversion {
                 factor: conv1.factor / conv2.factor,
@@ -239,19 +258,7 @@ impl Div for Unit {
             }),
             (None, None) => None,
         };
-        let simple_unit = self.simple_unit / rhs.simple_unit;
-        // println!("simple unit {:?}, conversion: {:?}", simple_unit, conversion);
-        if let Some(ref conversion) = conversion {
-            assert_eq!(
-                simple_unit.dimension.dimensions,
-                conversion.base_unit.dimension.dimensions
-            );
-        }
-
-        Unit {
-            simple_unit,
-            conversion,
-        }
+        Unit::new(self.simple_unit / rhs.simple_unit, conversion)
     }
 }
 
@@ -260,8 +267,8 @@ impl Unit {
     /// Enfore invariarnts that the dimension of the unit and the base unit of the conversion match
     pub fn new(simple_unit: SimpleUnit, conversion: Option<Conversion>) -> Result<Self> {
         if let Some(ref conversion) = conversion {
-            // name can be different (for now) but dimensions vector should be the same
-            if simple_unit.dimension.dimensions != conversion.base_unit.dimension.dimensions {
+            // name can be different (for now) but the dimensions should be the same, in any order
+            if !simple_unit.dimension.is_compatible(&conversion.base_unit.dimension) {
                 bail!(
                     "Dimension mismatch between unit and conversion base unit, got {:?} and {:?}, while creating {:?}",
                     simple_unit.dimension,
@@ -296,32 +303,115 @@ impl Unit {
     }
 
     pub fn pow(&self, exp: i64) -> Self {
+        self.pow_rat(Rational64::from_integer(exp))
+    }
+
+    pub fn pow_rat(&self, exp: Rational64) -> Self {
         let new_conversion = self.conversion.as_ref().map(|conv| Conversion {
-            factor: conv.factor.powi(exp as i32),
+            factor: if exp.is_integer() {
+                conv.factor.powi(exp.to_integer() as i32)
+            } else {
+                conv.factor.powf(*exp.numer() as f64 / *exp.denom() as f64)
+            },
             offset: None,
-            base_unit: conv.base_unit.clone().pow(exp),
+            base_unit: conv.base_unit.pow_rat(exp),
         });
         Self {
-            simple_unit: self.simple_unit.pow(exp),
+            simple_unit: self.simple_unit.pow_rat(exp),
             conversion: new_conversion,
         }
     }
 
+    /// Conversion of a unit defined as `value * conv_factor + conv_offset` in this unit, chained through the
+    /// conversion of this unit so that it is always relative to base units
+    fn derived_conversion(&self, conv_factor: f64, conv_offset: Option<f64>) -> Conversion {
+        match self.conversion {
+            Some(ref base_conv) => {
+                let new_factor = conv_factor * base_conv.factor;
+                // value in the root base unit: (value * factor + offset) * base_factor + base_offset
+                let new_offset = match (conv_offset, base_conv.offset) {
+                    (None, None) => None,
+                    (offset, base_offset) => {
+                        Some(offset.unwrap_or(0.0) * base_conv.factor + base_offset.unwrap_or(0.0))
+                    },
+                };
+                Conversion {
+                    factor: new_factor,
+                    offset: new_offset,
+                    base_unit: base_conv.base_unit.clone(),
+                }
+            },
+            None => Conversion {
+                factor: conv_factor,
+                offset: conv_offset,
+                base_unit: self.simple_unit.clone(),
+            },
+        }
+    }
+
+    /// `(factor, offset)` to the base unit, such that `base = value * factor + offset`
+    fn to_base(&self) -> (f64, f64) {
+        match &self.conversion {
+            Some(conv) => (conv.factor, conv.offset.unwrap_or(0.0)),
+            None => (1.0, 0.0),
+        }
+    }
+
+    pub fn dimension(&self) -> &Dimension {
+        &self.simple_unit.dimension
+    }
+
+    /// Whether the unit has a non zero offset to its base unit (e.g. `degree_Celsius`)
+    pub fn has_offset(&self) -> bool {
+        self.to_base().1 != 0.0
+    }
+
+    /// The dimensionless unit, i.e. the neutral element of unit multiplication
+    pub fn dimensionless() -> Self {
+        Self::new_simple("dimensionless", Dimension::new("dimensionless", vec![]))
+    }
+
+    /// The unit scaled by a prefix factor (e.g. `kilo`), with `name` as the new unit name
+    pub fn with_prefix(&self, name: &str, prefix_factor: f64) -> Self {
+        let conversion = match &self.conversion {
+            Some(conv) => Conversion {
+                factor: prefix_factor * conv.factor,
+                offset: conv.offset,
+                base_unit: conv.base_unit.clone(),
+            },
+            None => Conversion::new(prefix_factor, self.simple_unit.clone()),
+        };
+        Self {
+            simple_unit: SimpleUnit {
+                name: name.to_string(),
+                dimension: self.simple_unit.dimension.clone(),
+            },
+            conversion: Some(conversion),
+        }
+    }
+
     #[cfg(test)]
     pub fn name(&self) -> &str {
         self.simple_unit.name.as_str()
     }
 }
 
+#[derive(Clone)]
 pub struct UnitRegistry {
     pub dimensions: HashMap<String, Dimension>,
     pub units: HashMap<String, Unit>,
+    /// prefix name or symbol (e.g. `kilo` or `k`) to its factor, prefixed units are resolved on the fly by `get_unit`
+    pub prefixes: HashMap<String, f64>,
+    /// symbols, plurals and alternative spellings (e.g. `m`, `meters`, `metre`) to the unit name
+    pub aliases: HashMap<String, String>,
 }
 impl UnitRegistry {
     pub fn new() -> Self {
         Self {
             dimensions: HashMap::new(),
             units: HashMap::new(),
+            prefixes: HashMap::new(),
+            aliases: HashMap::new(),
         }
     }
 
@@ -333,12 +423,43 @@ impl UnitRegistry {
     }
 
     pub fn get_unit(&self, name: &str) -> Result<Unit> {
-        self.units
-            .get(name)
+        self.lookup_unit(name)
             .cloned()
+            .or_else(|| self.get_prefixed_unit(name))
             .context(format!("unit {} not found", name))
     }
 
+    /// Unit by name or alias, without prefix resolution
+    fn lookup_unit(&self, name: &str) -> Option<&Unit> {
+        self.units
+            .get(name)
+            .or_else(|| self.aliases.get(name).and_then(|name| self.units.get(name)))
+    }
+
+    /// Resolve a prefixed unit (e.g. `kilometer`) by splitting the name into a known prefix and a known unit
+    fn get_prefixed_unit(&self, name: &str) -> Option<Unit> {
+        let (prefix, rest) = self.split_prefix(name)?;
+        let unit = self.lookup_unit(rest)?;
+        Some(unit.with_prefix(name, self.prefixes[prefix]))
+    }
+
+    /// Split the name of a prefixed unit into a known prefix and a known unit (e.g. `km` into `k` and `m`). The
+    /// longest matching prefix wins, names of units (e.g. `ft`, not `f` and `t`) are not split
+    pub fn split_prefix<'a>(&self, name: &'a str) -> Option<(&'a str, &'a str)> {
+        // compound units like `meter^2` are registered as a whole, `kilometer^2` is not `kilo` * `meter^2`
+        if name.contains(['^', '*', '/']) || self.lookup_unit(name).is_some() {
+            return None;
+        }
+        self.prefixes
+            .keys()
+            .filter_map(|prefix| {
+                let rest = name.strip_prefix(prefix.as_str()).filter(|rest| !rest.is_empty())?;
+                self.lookup_unit(rest)?;
+                Some(name.split_at(prefix.len()))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+    }
+
     pub fn try_get_dimension(&self, name: &str) -> Dimension {
         self.get_dimension(name).unwrap()
     }
@@ -369,22 +490,9 @@ impl UnitRegistry {
         conv_offset: Option<f64>,
         conv_base_name: &str,
     ) {
-        let base_unit = self.try_get_unit(conv_base_name);
-        let conversion = match base_unit.conversion {
-            Some(ref base_conv) => {
-                let new_factor = conv_factor * base_conv.factor;
-                Conversion {
-                    factor: new_factor,
-                    offset: conv_offset,
-                    base_unit: base_conv.base_unit.clone(),
-                }
-            },
-            None => Conversion {
-                factor: conv_factor,
-                offset: conv_offset,
-                base_unit: base_unit.simple_unit.clone(),
-            },
-        };
+        let conversion = self
+            .try_get_unit(conv_base_name)
+            .derived_conversion(conv_factor, conv_offset);
         let unit = Unit::new(
             SimpleUnit {
                 name: name.to_string(),
@@ -396,6 +504,98 @@ impl UnitRegistry {
         self.add_unit(unit);
     }
 
+    /// Add a unit defined as `value * conv_factor + conv_offset` in a (possibly compound) unit, e.g. `watt*hour`
+    pub fn add_unit_relative_to(
+        &mut self,
+        name: &str,
+        conv_factor: f64,
+        conv_offset: Option<f64>,
+        base_unit: &Unit,
+    ) -> Result<()> {
+        let unit = Unit::new(
+            SimpleUnit {
+                name: name.to_string(),
+                dimension: base_unit.simple_unit.dimension.clone(),
+            },
+            Some(base_unit.derived_conversion(conv_factor, conv_offset)),
+        )?;
+        self.add_unit(unit);
+        Ok(())
+    }
+
+    pub fn add_prefix(&mut self, name: &str, factor: f64) {
+        self.prefixes.insert(name.to_string(), factor);
+    }
+
+    /// Register a symbol for an existing prefix, e.g. `k` for `kilo`
+    pub fn add_prefix_alias(&mut self, alias: &str, prefix: &str) {
+        let factor = *self
+            .prefixes
+            .get(prefix)
+            .unwrap_or_else(|| panic!("prefix {} not found", prefix));
+        self.add_prefix(alias, factor);
+    }
+
+    /// Register a symbol or alternative name for an existing unit, e.g. `m` for `meter`
+    pub fn add_alias(&mut self, alias: &str, unit_name: &str) {
+        assert!(
+            self.units.contains_key(unit_name),
+            "cannot add alias {} for unknown unit {}",
+            alias,
+            unit_name
+        );
+        self.aliases.insert(alias.to_string(), unit_name.to_string());
+    }
+
+    /// Register plurals and british spellings of a unit (e.g. `meters`, `metre` and `metres` for `meter`),
+    /// without overriding existing units or aliases
+    pub fn add_spelling_variants(&mut self, unit_name: &str) {
+        // compound units such as `meter/second` are not spelled out
+        if unit_name.contains(['^', '*', '/']) {
+            return;
+        }
+        let mut variants: Vec<String> = plural(unit_name).into_iter().collect();
+        if let Some(british) = british_spelling(unit_name) {
+            variants.extend(plural(&british));
+            variants.push(british);
+        }
+        for variant in variants {
+            if !self.units.contains_key(&variant) && !self.aliases.contains_key(&variant) {
+                self.aliases.insert(variant, unit_name.to_string());
+            }
+        }
+    }
+
+    /// Register a `delta_` unit for every offset unit and its aliases (e.g. `delta_degC` for `degC`), which measures
+    /// differences and converts without the offset. Symbol aliases (e.g. `°C`) are skipped, as `delta_°C` can't be parsed
+    pub fn add_delta_units(&mut self) {
+        let mut offset_units: Vec<Unit> = self.units.values().filter(|unit| unit.has_offset()).cloned().collect();
+        offset_units.sort_by(|a, b| a.simple_unit.name.cmp(&b.simple_unit.name));
+        for unit in offset_units {
+            let conversion = unit.conversion.expect("offset units have a conversion");
+            let delta_name = format!("delta_{}", unit.simple_unit.name);
+            self.add_unit(Unit {
+                simple_unit: SimpleUnit {
+                    name: delta_name.clone(),
+                    dimension: unit.simple_unit.dimension,
+                },
+                conversion: Some(Conversion::new(conversion.factor, conversion.base_unit)),
+            });
+            let mut aliases: Vec<String> = self
+                .aliases
+                .iter()
+                .filter(|(alias, target)| {
+                    **target == unit.simple_unit.name && alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                })
+                .map(|(alias, _)| alias.clone())
+                .collect();
+            aliases.sort();
+            for alias in aliases {
+                self.add_alias(&format!("delta_{}", alias), &delta_name);
+            }
+        }
+    }
+
     pub fn add_dimension(&mut self, dimension: Dimension) {
         self.dimensions.insert(dimension.name.to_string(), dimension);
     }
@@ -404,64 +604,134 @@ impl UnitRegistry {
         let dimension = Dimension::new_simple(name);
         self.add_dimension(dimension);
     }
-    pub fn convert_units(old_unit: Unit, new_unit: Unit) -> Result<f64> {
+    /// Affine conversion `(factor, offset)` between two units, such that `new = old * factor + offset`.
+    /// The offset is zero unless one of the units is an offset unit (e.g. `degree_Celsius`)
+    pub fn convert_units(old_unit: Unit, new_unit: Unit) -> Result<(f64, f64)> {
         let old_dim = &old_unit.simple_unit.dimension;
         let new_dim = &new_unit.simple_unit.dimension;
-        if old_dim != new_dim {
+        if !old_dim.is_compatible(new_dim) {
             bail!(
                 "Cannot convert between units with different dimensions, got {:?} and {:?}",
                 old_dim,
                 new_dim
             );
         }
-        let old_conv = old_unit.conversion;
-        let new_conv = new_unit.conversion;
-        match (old_conv, new_conv) {
-            (Some(old_conv), Some(new_conv)) => {
-                if old_conv.base_unit == new_conv.base_unit {
-                    assert!(
-                        old_conv.offset.is_none() & new_conv.offset.is_none(),
-                        "Offset not yet supported"
-                    );
-                    let factor = old_conv.factor / new_conv.factor;
-                    Ok(factor)
-                } else {
-                    bail!("Cannot convert between units with different dimensions");
-                }
-            },
-            (Some(old_conv), None) => {
-                let factor = old_conv.factor;
-                assert!(old_conv.offset.is_none());
-                Ok(factor)
-            },
-            (None, Some(new_conv)) => {
-                let factor = 1.0 / new_conv.factor;
-                assert!(new_conv.offset.is_none());
-                Ok(factor)
-            },
-            (None, None) => {
-                if old_unit.simple_unit == new_unit.simple_unit {
-                    Ok(1.0)
-                } else {
-                    Err(anyhow!(
-                        "Cannot convert between units with different dimensions, got {:?} {:?}",
-                        old_unit.simple_unit,
-                        new_unit.simple_unit
-                    ))
-                }
-            },
+        // units without a conversion are coherent base units (e.g. meter, meter/second), so the factor
+        // of a conversion is always relative to base units of the same dimension
+        let (old_factor, old_offset) = old_unit.to_base();
+        let (new_factor, new_offset) = new_unit.to_base();
+        // old -> base: `old * old_factor + old_offset`, base -> new: `(base - new_offset) / new_factor`
+        Ok((old_factor / new_factor, (old_offset - new_offset) / new_factor))
+    }
+
+    /// Resolve a product of units (e.g. `kg*m^2/s^2`) to a registry unit, looking up each factor by name
+    /// A single offset unit (e.g. `degC`) keeps its offset, in products of units (e.g. `degC/s`) offset units are rejected
+    /// as the offset is not well defined
+    pub fn resolve(&self, units: &Units) -> Result<Unit> {
+        if let [unit] = units.units.as_slice() {
+            if unit.power == Rational64::from_integer(1) {
+                return self.get_unit(&unit.name);
+            }
+        }
+        units.units.iter().try_fold(Unit::dimensionless(), |acc, unit| {
+            let resolved = self.get_unit(&unit.name)?;
+            if resolved.has_offset() {
+                bail!(
+                    "Offset unit {} cannot be used in a product of units or with a power, got {}",
+                    unit.name,
+                    units
+                );
+            }
+            acc * resolved.pow_rat(unit.power)
+        })
+    }
+
+    /// The resolved unit if `units` is a single offset unit (e.g. `degC`)
+    fn offset_unit(&self, units: &Units) -> Option<Unit> {
+        self.resolve(units).ok().filter(Unit::has_offset)
+    }
+
+    pub fn is_offset_unit(&self, units: &Units) -> bool {
+        self.offset_unit(units).is_some()
+    }
+
+    /// Whether `units` is a temperature difference unit, e.g. `delta_degC`
+    pub fn is_delta_unit(&self, units: &Units) -> bool {
+        matches!(self.resolve(units), Ok(unit) if unit.simple_unit.name.starts_with("delta_"))
+    }
+
+    /// The delta unit of an offset unit, keeping the spelling of the input when possible (`degC` -> `delta_degC`)
+    pub fn delta_unit(&self, units: &Units) -> Result<Units> {
+        let unit = self
+            .offset_unit(units)
+            .with_context(|| format!("{} is not an offset unit", units))?;
+        let name = format!("delta_{}", units.units[0].name);
+        if self.get_unit(&name).is_ok() {
+            Ok(Units::new_simple(&name))
+        } else {
+            Ok(Units::new_simple(&format!("delta_{}", unit.simple_unit.name)))
         }
     }
 
+    /// The absolute base unit of an offset unit, e.g. `kelvin` for `degC`
+    pub fn absolute_unit(&self, units: &Units) -> Result<Units> {
+        let unit = self
+            .offset_unit(units)
+            .with_context(|| format!("{} is not an offset unit", units))?;
+        let conversion = unit.conversion.expect("offset units have a conversion");
+        Ok(Units::new_simple(&conversion.base_unit.name))
+    }
+
+    /// Affine conversion `(factor, offset)` between two products of units, which must have the same dimensions
+    pub fn convert_between(&self, unit_from: &Units, unit_to: &Units) -> Result<(f64, f64)> {
+        let (from, to) = (self.resolve(unit_from)?, self.resolve(unit_to)?);
+        Self::convert_units(from, to).with_context(|| format!("while converting {} to {}", unit_from, unit_to))
+    }
+
+    /// Conversion factor between two unit expressions, e.g. `m/s` and `km/h`
+    #[cfg(test)]
     pub fn convert(&self, unit_from: String, unit_to: String) -> Result<f64> {
-        let unit_from = self.units.get(&unit_from).unwrap();
-        let unit_to = self.units.get(&unit_to).unwrap();
-        Self::convert_units(unit_from.clone(), unit_to.clone())
+        let (factor, offset) = self.convert_between(&unit_from.parse()?, &unit_to.parse()?)?;
+        assert_eq!(offset, 0.0, "use convert_between for offset units");
+        Ok(factor)
     }
 }
 
 impl Dimension {}
 
+/// English plural of a unit name, only the first word of `x_per_y` names is pluralized (`miles_per_hour`).
+/// Names ending in `s`, `x` or `z` (e.g. `hertz`, `lux`, `siemens`) are considered invariant
+fn plural(name: &str) -> Option<String> {
+    if let Some((first, rest)) = name.split_once("_per_") {
+        return plural(first).map(|first| format!("{}_per_{}", first, rest));
+    }
+    let irregular = [("foot", "feet"), ("millennium", "millennia")];
+    if let Some((singular, plural)) = irregular.iter().find(|(singular, _)| name.ends_with(singular)) {
+        return Some(format!("{}{}", &name[..name.len() - singular.len()], plural));
+    }
+    if name.ends_with(['s', 'x', 'z']) || !name.ends_with(|c: char| c.is_ascii_lowercase()) {
+        None
+    } else if name.ends_with("ch") || name.ends_with("sh") {
+        Some(format!("{}es", name))
+    } else if let Some(stem) = name
+        .strip_suffix('y')
+        .filter(|stem| !stem.ends_with(['a', 'e', 'i', 'o', 'u']))
+    {
+        Some(format!("{}ies", stem))
+    } else {
+        Some(format!("{}s", name))
+    }
+}
+
+/// British spelling of a unit name, e.g. `metre` for `meter` and `litre` for `liter`
+fn british_spelling(name: &str) -> Option<String> {
+    if name.contains("meter") || name.contains("liter") {
+        Some(name.replace("meter", "metre").replace("liter", "litre"))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -526,7 +796,7 @@ mod tests {
     #[test]
     fn test_unit_multiplication_basic() {
         let (meter, _, _) = setup_length_units();
-        let result = meter.clone() * meter.clone();
+        let result = (meter.clone() * meter.clone()).unwrap();
 
         assert_eq!(
             result.simple_unit.dimension.dimensions[0].1,
@@ -538,8 +808,8 @@ mod tests {
     #[test]
     fn test_unit_multiplication_with_conversions() {
         let (meter, kilometer, _) = setup_length_units();
-        let result = kilometer.clone() * kilometer.clone();
-        let m2 = meter.clone() * meter.clone();
+        let result = (kilometer.clone() * kilometer.clone()).unwrap();
+        let m2 = (meter.clone() * meter.clone()).unwrap();
 
         assert!(result.conversion.is_some());
         if let Some(conv) = result.conversion {
@@ -551,8 +821,8 @@ mod tests {
     #[test]
     fn test_unit_multiplication_mixed_conversion() {
         let (meter, kilometer, _) = setup_length_units();
-        let result = meter.clone() * kilometer;
-        let m2 = meter.clone() * meter.clone();
+        let result = (meter.clone() * kilometer).unwrap();
+        let m2 = (meter.clone() * meter.clone()).unwrap();
 
         assert!(result.conversion.is_some());
         if let Some(conv) = result.conversion {
@@ -565,15 +835,15 @@ mod tests {
     #[test]
     fn test_unit_division_basic() {
         let (meter, _, _) = setup_length_units();
-        let result = meter.clone() / meter.clone();
+        let result = (meter.clone() / meter.clone()).unwrap();
         assert!(result.simple_unit.dimension.dimensions.is_empty());
     }
 
     #[test]
     fn test_unit_division_with_conversions() {
         let (meter, kilometer, _) = setup_length_units();
-        let result = kilometer.clone() / kilometer.clone();
-        let m_m = meter.clone() / meter.clone();
+        let result = (kilometer.clone() / kilometer.clone()).unwrap();
+        let m_m = (meter.clone() / meter.clone()).unwrap();
 
         assert!(result.conversion.is_some());
         if let Some(conv) = result.conversion {
@@ -588,7 +858,7 @@ mod tests {
     #[test]
     fn test_unit_division_mixed_conversion() {
         let (meter, kilometer, _) = setup_length_units();
-        let result = kilometer / meter;
+        let result = (kilometer / meter).unwrap();
 
         assert!(result.conversion.is_some());
         if let Some(conv) = result.conversion {
@@ -624,28 +894,28 @@ mod tests {
     #[test]
     fn test_conversion_same_unit() {
         let (meter, _, _) = setup_length_units();
-        let factor = UnitRegistry::convert_units(meter.clone(), meter.clone()).unwrap();
+        let (factor, _) = UnitRegistry::convert_units(meter.clone(), meter.clone()).unwrap();
         assert_eq!(factor, 1.0);
     }
 
     #[test]
     fn test_conversion_to_larger_unit() {
         let (meter, kilometer, _) = setup_length_units();
-        let factor = UnitRegistry::convert_units(meter, kilometer).unwrap();
+        let (factor, _) = UnitRegistry::convert_units(meter, kilometer).unwrap();
         assert_eq!(factor, 0.001);
     }
 
     #[test]
     fn test_conversion_to_smaller_unit() {
         let (meter, _, centimeter) = setup_length_units();
-        let factor = UnitRegistry::convert_units(meter, centimeter).unwrap();
+        let (factor, _) = UnitRegistry::convert_units(meter, centimeter).unwrap();
         assert_eq!(factor, 100.0);
     }
 
     #[test]
     fn test_conversion_between_derived_units() {
         let (_meter, kilometer, centimeter) = setup_length_units();
-        let factor = UnitRegistry::convert_units(kilometer, centimeter).unwrap();
+        let (factor, _) = UnitRegistry::convert_units(kilometer, centimeter).unwrap();
         assert_eq!(factor, 100_000.0);
     }
 
@@ -659,22 +929,64 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Offset not yet supported")]
     fn test_conversion_with_offset() {
-        let length = Dimension::new_simple("length");
-        let meter = Unit::new_simple("meter", length.clone());
+        let mut registry = UnitRegistry::new();
+        registry.add_dimension_simple("temperature");
+        registry.add_unit_simple("kelvin", "[temperature]");
+        registry.add_unit_deriv_offset("degree_Celsius", "[temperature]", 1.0, Some(273.15), "kelvin");
+        registry.add_unit_deriv_offset("degree_Rankine", "[temperature]", 5.0 / 9.0, None, "kelvin");
+        // defined through another offset unit, the offsets are chained
+        registry.add_unit_deriv_offset(
+            "degree_Fahrenheit",
+            "[temperature]",
+            1.0,
+            Some(459.67),
+            "degree_Rankine",
+        );
 
-        let meter_with_offset = Unit {
-            simple_unit: meter.simple_unit.clone(),
-            conversion: Some(Conversion {
-                factor: 1.0,
-                offset: Some(10.0),
-                base_unit: meter.simple_unit.clone(),
-            }),
+        let convert = |from: &str, to: &str, value: f64| {
+            let (factor, offset) =
+                UnitRegistry::convert_units(registry.try_get_unit(from), registry.try_get_unit(to)).unwrap();
+            value * factor + offset
         };
+        assert!(is_close::is_close!(convert("degree_Celsius", "kelvin", 20.0), 293.15));
+        assert!(is_close::is_close!(convert("kelvin", "degree_Celsius", 0.0), -273.15));
+        assert!(is_close::is_close!(
+            convert("degree_Fahrenheit", "degree_Celsius", 212.0),
+            100.0
+        ));
+        assert!(is_close::is_close!(
+            convert("degree_Celsius", "degree_Fahrenheit", -40.0),
+            -40.0
+        ));
+        assert!(is_close::is_close!(
+            convert("degree_Fahrenheit", "kelvin", 32.0),
+            273.15
+        ));
+        assert!(is_close::is_close!(
+            convert("degree_Rankine", "degree_Fahrenheit", 0.0),
+            -459.67
+        ));
+    }
 
-        let _ = UnitRegistry::convert_units(meter_with_offset.clone(), meter_with_offset.clone());
+    #[test]
+    fn test_resolve_offset_unit_in_product() {
+        let mut registry = UnitRegistry::new();
+        registry.add_dimension_simple("temperature");
+        registry.add_dimension_simple("time");
+        registry.add_unit_simple("kelvin", "[temperature]");
+        registry.add_unit_simple("second", "[time]");
+        registry.add_unit_deriv_offset("degree_Celsius", "[temperature]", 1.0, Some(273.15), "kelvin");
+
+        assert!(registry
+            .resolve(&"degree_Celsius".parse().unwrap())
+            .unwrap()
+            .has_offset());
+        assert!(registry.resolve(&"kelvin/second".parse().unwrap()).is_ok());
+        assert!(registry.resolve(&"degree_Celsius/second".parse().unwrap()).is_err());
+        assert!(registry.resolve(&"degree_Celsius^2".parse().unwrap()).is_err());
     }
+
     // Additional edge cases and complex scenarios
     #[test]
     fn test_complex_dimension_multiplication() {
@@ -688,7 +1000,7 @@ mod tests {
 
         let unit1 = Unit::new_simple("unit1", dim.clone());
 
-        let result = unit1.clone() * unit1.clone();
+        let result = (unit1.clone() * unit1.clone()).unwrap();
         assert_eq!(
             result.simple_unit.dimension.dimensions[0].1,
             Rational64::from_integer(2)
@@ -726,6 +1038,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_unit_new_dimensions_in_any_order() {
+        let length = ("[length]".to_string(), Rational64::from_integer(1));
+        let time = ("[time]".to_string(), Rational64::from_integer(-1));
+        let base_unit = SimpleUnit {
+            name: "meter/second".to_string(),
+            dimension: Dimension::new("speed", vec![length.clone(), time.clone()]),
+        };
+        let knot = SimpleUnit {
+            name: "knot".to_string(),
+            dimension: Dimension::new("speed", vec![time, length]),
+        };
+
+        assert!(Unit::new(knot, Some(Conversion::new(0.514444, base_unit))).is_ok());
+    }
+
+    #[test]
+    fn test_unit_multiplication_dimension_mismatch() {
+        let (meter, _, _) = setup_length_units();
+        // skips the validation of Unit::new, like a unit with a wrong conversion
+        let bad_unit = Unit {
+            simple_unit: SimpleUnit {
+                name: "bad".to_string(),
+                dimension: Dimension::new_simple("time"),
+            },
+            conversion: Some(Conversion::new(2.0, meter.simple_unit.clone())),
+        };
+
+        assert!((bad_unit.clone() * meter.clone()).is_err());
+        assert!((meter / bad_unit).is_err());
+    }
+
     #[test]
     fn test_add_unit_deriv_offset_base_unit_no_conversion() {
         let mut registry = UnitRegistry::new();
@@ -770,6 +1114,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_get_prefixed_unit() {
+        let mut registry = UnitRegistry::new();
+        registry.add_dimension_simple("length");
+        registry.add_unit_simple("meter", "[length]");
+        registry.add_unit_deriv("mile", "[length]", 1609.344, "meter");
+        registry.add_unit(registry.try_get_unit("meter").pow(2));
+        registry.add_prefix("kilo", 1e3);
+        registry.add_prefix("milli", 1e-3);
+
+        let kilometer = registry.get_unit("kilometer").unwrap();
+        assert_eq!(kilometer.name(), "kilometer");
+        assert_eq!(
+            kilometer.simple_unit.dimension,
+            registry.try_get_unit("meter").simple_unit.dimension
+        );
+        assert_eq!(
+            UnitRegistry::convert_units(kilometer, registry.try_get_unit("meter")).unwrap(),
+            (1000.0, 0.0)
+        );
+
+        let millimile = registry.get_unit("millimile").unwrap();
+        assert!(is_close::is_close!(
+            UnitRegistry::convert_units(millimile, registry.try_get_unit("meter"))
+                .unwrap()
+                .0,
+            1.609344
+        ));
+
+        assert!(registry.get_unit("kilo").is_err());
+        assert!(registry.get_unit("megameter").is_err());
+        assert!(registry.get_unit("kilometer^2").is_err());
+    }
+
+    #[test]
+    fn test_plural() {
+        assert_eq!(plural("meter").as_deref(), Some("meters"));
+        assert_eq!(plural("inch").as_deref(), Some("inches"));
+        assert_eq!(plural("century").as_deref(), Some("centuries"));
+        assert_eq!(plural("day").as_deref(), Some("days"));
+        assert_eq!(plural("foot").as_deref(), Some("feet"));
+        assert_eq!(plural("survey_foot").as_deref(), Some("survey_feet"));
+        assert_eq!(plural("mile_per_hour").as_deref(), Some("miles_per_hour"));
+        assert_eq!(plural("hertz"), None);
+        assert_eq!(plural("siemens"), None);
+        assert_eq!(plural("US_ton").as_deref(), Some("US_tons"));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut registry = UnitRegistry::new();
+        registry.add_dimension_simple("length");
+        registry.add_unit_simple("meter", "[length]");
+        registry.add_prefix("kilo", 1e3);
+        registry.add_prefix_alias("k", "kilo");
+        registry.add_alias("m", "meter");
+        registry.add_spelling_variants("meter");
+
+        for name in ["m", "meter", "meters", "metre", "metres"] {
+            assert_eq!(registry.get_unit(name).unwrap().name(), "meter", "{}", name);
+        }
+        for name in ["km", "kilometer", "kilometres", "kmeter"] {
+            let unit = registry.get_unit(name).unwrap();
+            assert_eq!(unit.name(), name);
+            assert_eq!(
+                UnitRegistry::convert_units(unit, registry.try_get_unit("m")).unwrap(),
+                (1000.0, 0.0)
+            );
+        }
+    }
+
     #[test]
     fn test_unit_simplify_zero_numer() {
         let mut dim = Dimension {
@@ -783,7 +1198,7 @@ mod tests {
     #[test]
     fn test_unit_simplify() {
         let (meter, _, _) = setup_length_units();
-        let result = meter.clone() / meter.clone();
+        let result = (meter.clone() / meter.clone()).unwrap();
         assert!(result.simple_unit.dimension.dimensions.is_empty());
     }
 }
@@ -1,4 +1,6 @@
 #![allow(clippy::excessive_precision)]
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
 use once_cell::sync::Lazy;
 
 use super::conversion::*;
@@ -10,6 +12,7 @@ impl UnitRegistry {
     fn new_with_definitions() -> Self {
         let mut registry = UnitRegistry::new();
         registry.add_base_dimensions();
+        registry.add_prefixes();
         registry.add_base_units();
         registry.add_angles();
         registry.add_information();
@@ -17,6 +20,7 @@ impl UnitRegistry {
         registry.add_length();
         registry.add_mass();
         registry.add_time();
+        registry.add_temperature();
         registry.add_area();
         registry.add_volume();
         registry.add_frequency();
@@ -76,6 +80,9 @@ impl UnitRegistry {
         registry.add_apothecary();
         registry.add_imperial_volume();
         registry.add_printer();
+        registry.add_symbols();
+        registry.add_delta_units();
+        registry.add_all_spelling_variants();
         registry
     }
 
@@ -90,6 +97,79 @@ impl UnitRegistry {
         self.add_dimension(Dimension::new("dimensionless", vec![]));
     }
 
+    fn add_prefixes(&mut self) {
+        // SI
+        self.add_prefix("quecto", 1e-30);
+        self.add_prefix("ronto", 1e-27);
+        self.add_prefix("yocto", 1e-24);
+        self.add_prefix("zepto", 1e-21);
+        self.add_prefix("atto", 1e-18);
+        self.add_prefix("femto", 1e-15);
+        self.add_prefix("pico", 1e-12);
+        self.add_prefix("nano", 1e-9);
+        self.add_prefix("micro", 1e-6);
+        self.add_prefix("milli", 1e-3);
+        self.add_prefix("centi", 1e-2);
+        self.add_prefix("deci", 1e-1);
+        self.add_prefix("deca", 1e1);
+        self.add_prefix("deka", 1e1);
+        self.add_prefix("hecto", 1e2);
+        self.add_prefix("kilo", 1e3);
+        self.add_prefix("mega", 1e6);
+        self.add_prefix("giga", 1e9);
+        self.add_prefix("tera", 1e12);
+        self.add_prefix("peta", 1e15);
+        self.add_prefix("exa", 1e18);
+        self.add_prefix("zetta", 1e21);
+        self.add_prefix("yotta", 1e24);
+        self.add_prefix("ronna", 1e27);
+        self.add_prefix("quetta", 1e30);
+        // binary
+        self.add_prefix("kibi", 1024.0); // 2**10
+        self.add_prefix("mebi", 1048576.0); // 2**20
+        self.add_prefix("gibi", 1073741824.0); // 2**30
+        self.add_prefix("tebi", 1099511627776.0); // 2**40
+        self.add_prefix("pebi", 1125899906842624.0); // 2**50
+        self.add_prefix("exbi", 1152921504606846976.0); // 2**60
+
+        for (symbol, prefix) in [
+            ("q", "quecto"),
+            ("r", "ronto"),
+            ("y", "yocto"),
+            ("z", "zepto"),
+            ("a", "atto"),
+            ("f", "femto"),
+            ("p", "pico"),
+            ("n", "nano"),
+            ("µ", "micro"), // micro sign
+            ("μ", "micro"), // greek mu
+            ("u", "micro"),
+            ("m", "milli"),
+            ("c", "centi"),
+            ("d", "deci"),
+            ("da", "deca"),
+            ("h", "hecto"),
+            ("k", "kilo"),
+            ("M", "mega"),
+            ("G", "giga"),
+            ("T", "tera"),
+            ("P", "peta"),
+            ("E", "exa"),
+            ("Z", "zetta"),
+            ("Y", "yotta"),
+            ("R", "ronna"),
+            ("Q", "quetta"),
+            ("Ki", "kibi"),
+            ("Mi", "mebi"),
+            ("Gi", "gibi"),
+            ("Ti", "tebi"),
+            ("Pi", "pebi"),
+            ("Ei", "exbi"),
+        ] {
+            self.add_prefix_alias(symbol, prefix);
+        }
+    }
+
     fn add_base_units(&mut self) {
         self.add_unit_simple("meter", "[length]");
         self.add_unit_simple("kilogram", "[mass]");
@@ -160,6 +240,7 @@ impl UnitRegistry {
     }
 
     fn add_mass(&mut self) {
+        self.add_unit_deriv("gram", "[mass]", 1e-3, "kilogram");
         self.add_unit_deriv("metric_ton", "[mass]", 1e3, "kilogram");
         self.add_unit_deriv("unified_atomic_mass_unit", "[mass]", 1.66053906660e-27, "kilogram"); // 1 u = 1.66053906660e-27 kg
         self.add_unit_deriv("dalton", "[mass]", 1.66053906660e-27, "kilogram"); // 1 Da = 1.66053906660e-27 kg
@@ -195,6 +276,23 @@ impl UnitRegistry {
         self.add_unit_deriv("planck_time", "[time]", 5.39116e-44, "second"); // (hbar * gravitational_constant / c ** 5) ** 0.5
     }
 
+    fn add_temperature(&mut self) {
+        self.add_unit_deriv_offset("degree_Celsius", "[temperature]", 1.0, Some(273.15), "kelvin");
+        self.add_unit_deriv_offset("degree_Rankine", "[temperature]", 5.0 / 9.0, None, "kelvin");
+        // 233.15 + 200 / 9
+        self.add_unit_deriv_offset(
+            "degree_Fahrenheit",
+            "[temperature]",
+            5.0 / 9.0,
+            Some(233.15 + 200.0 / 9.0),
+            "kelvin",
+        );
+        self.add_unit_deriv_offset("degree_Reaumur", "[temperature]", 5.0 / 4.0, Some(273.15), "kelvin");
+        self.add_unit_deriv("atomic_unit_of_temperature", "[temperature]", 315775.02480407, "kelvin"); // E_h / k
+        self.add_unit_deriv("planck_temperature", "[temperature]", 1.416784e32, "kelvin");
+        // (hbar * c ** 5 / gravitational_constant / k ** 2) ** 0.5
+    }
+
     fn add_area(&mut self) {
         self.add_dimension(self.try_get_dimension("[length]").pow(2).with_name("[area]"));
         self.add_unit(self.try_get_unit("meter").pow(2));
@@ -234,7 +332,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[length]") / self.try_get_dimension("[time]")).with_name("[velocity]"),
         );
-        self.add_unit(self.try_get_unit("meter") / self.try_get_unit("second"));
+        self.add_unit((self.try_get_unit("meter") / self.try_get_unit("second")).unwrap());
         self.add_unit_deriv("knot", "[velocity]", 1852.0 / 3600.0, "meter/second"); // nautical_mile / hour
         self.add_unit_deriv("mile_per_hour", "[velocity]", 1609.344 / 3600.0, "meter/second"); // mile / hour
         self.add_unit_deriv("kilometer_per_hour", "[velocity]", 1000.0 / 3600.0, "meter/second"); // kilometer / hour
@@ -248,7 +346,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[volume]") / self.try_get_dimension("[time]")).with_name("[volumetric_flow_rate]"),
         );
-        self.add_unit(self.try_get_unit("meter^3") / self.try_get_unit("second"));
+        self.add_unit((self.try_get_unit("meter^3") / self.try_get_unit("second")).unwrap());
         self.add_unit_deriv("sverdrup", "[volumetric_flow_rate]", 1e6, "meter^3/second");
     }
 
@@ -256,7 +354,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[velocity]") / self.try_get_dimension("[time]")).with_name("[acceleration]"),
         );
-        self.add_unit(self.try_get_unit("meter") / self.try_get_unit("second").pow(2));
+        self.add_unit((self.try_get_unit("meter") / self.try_get_unit("second").pow(2)).unwrap());
         self.add_unit_deriv("galileo", "[acceleration]", 0.01, "meter/second^2");
         // centimeter / second^2
     }
@@ -264,7 +362,11 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[mass]") * self.try_get_dimension("[acceleration]")).with_name("[force]"),
         );
-        self.add_unit(self.try_get_unit("kilogram") * self.try_get_unit("meter") / self.try_get_unit("second").pow(2));
+        self.add_unit(
+            ((self.try_get_unit("kilogram") * self.try_get_unit("meter")).unwrap()
+                / self.try_get_unit("second").pow(2))
+            .unwrap(),
+        );
         self.add_unit_deriv("newton", "[force]", 1.0, "kilogram*meter/second^2");
         self.add_unit_deriv("dyne", "[force]", 1e-5, "newton"); // gram * centimeter / second^2
         self.add_unit_deriv("force_kilogram", "[force]", 9.80665, "newton"); // g_0 * kilogram
@@ -278,7 +380,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[force]") * self.try_get_dimension("[length]")).with_name("[energy]"),
         );
-        self.add_unit(self.try_get_unit("newton") * self.try_get_unit("meter"));
+        self.add_unit((self.try_get_unit("newton") * self.try_get_unit("meter")).unwrap());
         self.add_unit_deriv("joule", "[energy]", 1.0, "newton*meter");
         self.add_unit_deriv("erg", "[energy]", 1e-7, "joule"); // dyne * centimeter
         self.add_unit_deriv("watt_hour", "[energy]", 3600.0, "joule"); // watt * hour
@@ -311,7 +413,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[energy]") / self.try_get_dimension("[time]")).with_name("[power]"),
         );
-        self.add_unit(self.try_get_unit("joule") / self.try_get_unit("second"));
+        self.add_unit((self.try_get_unit("joule") / self.try_get_unit("second")).unwrap());
         self.add_unit_deriv("watt", "[power]", 1.0, "joule/second");
         self.add_unit_deriv("volt_ampere", "[power]", 1.0, "watt"); // volt * ampere
         self.add_unit_deriv("horsepower", "[power]", 745.69987158227022, "watt"); // 550 * foot * force_pound / second
@@ -330,14 +432,17 @@ impl UnitRegistry {
             (self.try_get_dimension("[length]") * self.try_get_dimension("[mass]") / self.try_get_dimension("[time]"))
                 .with_name("[momentum]"),
         );
-        self.add_unit(self.try_get_unit("kilogram") * self.try_get_unit("meter") / self.try_get_unit("second"));
+        self.add_unit(
+            ((self.try_get_unit("kilogram") * self.try_get_unit("meter")).unwrap() / self.try_get_unit("second"))
+                .unwrap(),
+        );
     }
 
     fn add_density(&mut self) {
         self.add_dimension(
             (self.try_get_dimension("[mass]") / self.try_get_dimension("[volume]")).with_name("[density]"),
         );
-        self.add_unit(self.try_get_unit("kilogram") / self.try_get_unit("liter"));
+        self.add_unit((self.try_get_unit("kilogram") / self.try_get_unit("liter")).unwrap());
         self.add_unit_deriv("mercury", "[density]", 13595.1, "kilogram/liter");
         self.add_unit_deriv("water", "[density]", 1.0, "kilogram/liter");
         self.add_unit_deriv("mercury_60F", "[density]", 13556.8, "kilogram/liter"); // approximate
@@ -350,7 +455,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[force]") / self.try_get_dimension("[area]")).with_name("[pressure]"),
         );
-        self.add_unit(self.try_get_unit("newton") / self.try_get_unit("meter^2"));
+        self.add_unit((self.try_get_unit("newton") / self.try_get_unit("meter^2")).unwrap());
         self.add_unit_deriv("pascal", "[pressure]", 1.0, "newton/meter^2");
         self.add_unit_deriv("barye", "[pressure]", 0.1, "pascal"); // dyne / centimeter^2
         self.add_unit_deriv("bar", "[pressure]", 1e5, "pascal");
@@ -374,7 +479,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[force]") * self.try_get_dimension("[length]")).with_name("[torque]"),
         );
-        self.add_unit(self.try_get_unit("newton") * self.try_get_unit("meter"));
+        self.add_unit((self.try_get_unit("newton") * self.try_get_unit("meter")).unwrap());
         self.add_unit_deriv("foot_pound", "[torque]", 1.3558179483314004, "newton*meter");
         // foot * force_pound
     }
@@ -383,7 +488,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[pressure]") * self.try_get_dimension("[time]")).with_name("[viscosity]"),
         );
-        self.add_unit(self.try_get_unit("pascal") * self.try_get_unit("second"));
+        self.add_unit((self.try_get_unit("pascal") * self.try_get_unit("second")).unwrap());
         self.add_unit_deriv("poise", "[viscosity]", 0.1, "pascal*second");
         self.add_unit_deriv("reyn", "[viscosity]", 6894.757293168, "pascal*second");
         // psi * second
@@ -393,7 +498,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[area]") / self.try_get_dimension("[time]")).with_name("[kinematic_viscosity]"),
         );
-        self.add_unit(self.try_get_unit("meter^2") / self.try_get_unit("second"));
+        self.add_unit((self.try_get_unit("meter^2") / self.try_get_unit("second")).unwrap());
         self.add_unit_deriv("stokes", "[kinematic_viscosity]", 1e-4, "meter^2/second");
         // centimeter^2 / second
     }
@@ -414,7 +519,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[substance]") / self.try_get_dimension("[volume]")).with_name("[concentration]"),
         );
-        self.add_unit(self.try_get_unit("mole") / self.try_get_unit("liter"));
+        self.add_unit((self.try_get_unit("mole") / self.try_get_unit("liter")).unwrap());
         self.add_unit_deriv("molar", "[concentration]", 1.0, "mole/liter");
     }
 
@@ -422,7 +527,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[substance]") / self.try_get_dimension("[time]")).with_name("[activity]"),
         );
-        self.add_unit(self.try_get_unit("mole") / self.try_get_unit("second"));
+        self.add_unit((self.try_get_unit("mole") / self.try_get_unit("second")).unwrap());
         self.add_unit_deriv("katal", "[activity]", 1.0, "mole/second");
         self.add_unit_deriv("enzyme_unit", "[activity]", 1.6666666666666667e-8, "mole/second");
         // micromole / minute
@@ -432,7 +537,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[energy]") / self.try_get_dimension("[temperature]")).with_name("[entropy]"),
         );
-        self.add_unit(self.try_get_unit("joule") / self.try_get_unit("kelvin"));
+        self.add_unit((self.try_get_unit("joule") / self.try_get_unit("kelvin")).unwrap());
         self.add_unit_deriv("clausius", "[entropy]", 4.184, "joule/kelvin"); // calorie / kelvin
     }
 
@@ -440,7 +545,9 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[entropy]") / self.try_get_dimension("[substance]")).with_name("[molar_entropy]"),
         );
-        self.add_unit(self.try_get_unit("joule") / self.try_get_unit("kelvin") / self.try_get_unit("mole"));
+        self.add_unit(
+            ((self.try_get_unit("joule") / self.try_get_unit("kelvin")).unwrap() / self.try_get_unit("mole")).unwrap(),
+        );
         self.add_unit_deriv("entropy_unit", "[molar_entropy]", 4.184, "joule/kelvin/mole");
         // calorie / kelvin / mole
     }
@@ -449,7 +556,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[current]") * self.try_get_dimension("[time]")).with_name("[charge]"),
         );
-        self.add_unit(self.try_get_unit("ampere") * self.try_get_unit("second"));
+        self.add_unit((self.try_get_unit("ampere") * self.try_get_unit("second")).unwrap());
         self.add_unit_deriv("coulomb", "[charge]", 1., "ampere*second");
         self.add_unit(self.try_get_unit("coulomb"));
         self.add_unit_deriv("abcoulomb", "[charge]", 10.0, "coulomb");
@@ -460,12 +567,12 @@ impl UnitRegistry {
 
     fn add_radiation(&mut self) {
         self.add_dimension(self.try_get_dimension("[time]").pow(-1).with_name("[radiation]"));
-        self.add_unit(self.try_get_unit("count") / self.try_get_unit("second"));
+        self.add_unit((self.try_get_unit("count") / self.try_get_unit("second")).unwrap());
         self.add_unit_deriv("becquerel", "[radiation]", 1., "count/second");
         self.add_unit_deriv("curie", "[radiation]", 3.7e10, "becquerel");
         self.add_unit_deriv("rutherford", "[radiation]", 1e6, "becquerel");
-        self.add_unit(self.try_get_unit("joule") / self.try_get_unit("kilogram"));
-        self.add_unit(self.try_get_unit("coulomb") / self.try_get_unit("kilogram"));
+        self.add_unit((self.try_get_unit("joule") / self.try_get_unit("kilogram")).unwrap());
+        self.add_unit((self.try_get_unit("coulomb") / self.try_get_unit("kilogram")).unwrap());
         self.add_dimension(self.try_get_dimension("[energy]") / self.try_get_dimension("[mass]"));
         self.add_unit_deriv("gray", "[energy]/[mass]", 1.0, "joule/kilogram");
         self.add_unit_deriv("sievert", "[energy]/[mass]", 1.0, "joule/kilogram");
@@ -478,7 +585,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[energy]") / self.try_get_dimension("[area]")).with_name("[heat_transmission]"),
         );
-        self.add_unit(self.try_get_unit("joule") / self.try_get_unit("meter^2"));
+        self.add_unit((self.try_get_unit("joule") / self.try_get_unit("meter^2")).unwrap());
         self.add_unit_deriv("peak_sun_hour", "[heat_transmission]", 3.6e6, "joule/meter^2"); // 1e3 * watt_hour / meter^2
         self.add_unit_deriv("langley", "[heat_transmission]", 41840.0, "joule/meter^2");
         // thermochemical_calorie / centimeter^2
@@ -488,7 +595,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[luminosity]") / self.try_get_dimension("[area]")).with_name("[luminance]"),
         );
-        self.add_unit(self.try_get_unit("candela") / self.try_get_unit("meter^2"));
+        self.add_unit((self.try_get_unit("candela") / self.try_get_unit("meter^2")).unwrap());
         self.add_unit_deriv("nit", "[luminance]", 1.0, "candela/meter^2");
         self.add_unit_deriv("stilb", "[luminance]", 1e4, "candela/meter^2"); // candela / centimeter^2
         self.add_unit_deriv("lambert", "[luminance]", 3183.098861837907, "candela/meter^2");
@@ -497,7 +604,7 @@ impl UnitRegistry {
 
     fn add_luminous_flux(&mut self) {
         self.add_dimension(self.try_get_dimension("[luminosity]").with_name("[luminous_flux]"));
-        self.add_unit(self.try_get_unit("candela") * self.try_get_unit("steradian"));
+        self.add_unit((self.try_get_unit("candela") * self.try_get_unit("steradian")).unwrap());
         self.add_unit_deriv("lumen", "[luminous_flux]", 1.0, "candela*steradian");
     }
 
@@ -505,14 +612,14 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[luminous_flux]") / self.try_get_dimension("[area]")).with_name("[illuminance]"),
         );
-        self.add_unit(self.try_get_unit("lumen") / self.try_get_unit("meter^2"));
+        self.add_unit((self.try_get_unit("lumen") / self.try_get_unit("meter^2")).unwrap());
         self.add_unit_deriv("lux", "[illuminance]", 1.0, "lumen/meter^2");
     }
     fn add_intensity(&mut self) {
         self.add_dimension(
             (self.try_get_dimension("[power]") / self.try_get_dimension("[area]")).with_name("[intensity]"),
         );
-        self.add_unit(self.try_get_unit("watt") / self.try_get_unit("meter^2"));
+        self.add_unit((self.try_get_unit("watt") / self.try_get_unit("meter^2")).unwrap());
         self.add_unit_deriv("atomic_unit_of_intensity", "[intensity]", 3.50944758e16, "watt/meter^2");
         // 0.5 * ε_0 * c * atomic_unit_of_electric_field^2
     }
@@ -532,7 +639,11 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[energy]") / self.try_get_dimension("[charge]")).with_name("[electric_potential]"),
         );
-        self.add_unit((self.try_get_unit("joule") / self.try_get_unit("coulomb")).with_name("volt"));
+        self.add_unit(
+            (self.try_get_unit("joule") / self.try_get_unit("coulomb"))
+                .unwrap()
+                .with_name("volt"),
+        );
         self.add_unit_deriv("abvolt", "[electric_potential]", 1e-8, "volt");
         self.add_unit_deriv("mean_international_volt", "[electric_potential]", 1.00034, "volt"); // approximate
         self.add_unit_deriv("US_international_volt", "[electric_potential]", 1.00033, "volt"); // approximate
@@ -550,7 +661,7 @@ impl UnitRegistry {
             (self.try_get_dimension("[electric_potential]") / self.try_get_dimension("[length]"))
                 .with_name("[electric_field]"),
         );
-        self.add_unit(self.try_get_unit("volt") / self.try_get_unit("meter"));
+        self.add_unit((self.try_get_unit("volt") / self.try_get_unit("meter")).unwrap());
         self.add_unit_deriv(
             "atomic_unit_of_electric_field",
             "[electric_field]",
@@ -564,7 +675,7 @@ impl UnitRegistry {
             (self.try_get_dimension("[charge]") / self.try_get_dimension("[area]"))
                 .with_name("[electric_displacement_field]"),
         );
-        self.add_unit(self.try_get_unit("coulomb") / self.try_get_unit("meter^2"));
+        self.add_unit((self.try_get_unit("coulomb") / self.try_get_unit("meter^2")).unwrap());
     }
     // this is an obscure unit that not even wikipedia can agree on how is defined, for now I am ignoring it as the dimensionlity don't add up
     // fn add_reduced_electric_field(&mut self) {
@@ -572,7 +683,7 @@ impl UnitRegistry {
     //         (self.try_get_dimension("[electric_field]") * self.try_get_dimension("[area]"))
     //             .with_name("[reduced_electric_field]"),
     //     );
-    //     self.add_unit(self.try_get_unit("volt") * self.try_get_unit("meter^2"));
+    //     self.add_unit((self.try_get_unit("volt") * self.try_get_unit("meter^2")).unwrap());
     //     self.add_unit_deriv("townsend", "[reduced_electric_field]", 1e-21, "volt*meter^2");
     // }
 
@@ -581,7 +692,11 @@ impl UnitRegistry {
             (self.try_get_dimension("[electric_potential]") / self.try_get_dimension("[current]"))
                 .with_name("[resistance]"),
         );
-        self.add_unit((self.try_get_unit("volt") / self.try_get_unit("ampere")).with_name("ohm"));
+        self.add_unit(
+            (self.try_get_unit("volt") / self.try_get_unit("ampere"))
+                .unwrap()
+                .with_name("ohm"),
+        );
         self.add_unit_deriv("abohm", "[resistance]", 1e-9, "ohm");
         self.add_unit_deriv("mean_international_ohm", "[resistance]", 1.00049, "ohm"); // approximate
         self.add_unit_deriv("US_international_ohm", "[resistance]", 1.000495, "ohm"); // approximate
@@ -592,7 +707,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[resistance]") * self.try_get_dimension("[length]")).with_name("[resistivity]"),
         );
-        self.add_unit(self.try_get_unit("ohm") * self.try_get_unit("meter"));
+        self.add_unit((self.try_get_unit("ohm") * self.try_get_unit("meter")).unwrap());
     }
 
     fn add_conductance(&mut self) {
@@ -600,7 +715,11 @@ impl UnitRegistry {
             (self.try_get_dimension("[current]") / self.try_get_dimension("[electric_potential]"))
                 .with_name("[conductance]"),
         );
-        self.add_unit((self.try_get_unit("ampere") / self.try_get_unit("volt")).with_name("siemens"));
+        self.add_unit(
+            (self.try_get_unit("ampere") / self.try_get_unit("volt"))
+                .unwrap()
+                .with_name("siemens"),
+        );
         self.add_unit_deriv("absiemens", "[conductance]", 1e9, "siemens");
     }
 
@@ -609,7 +728,11 @@ impl UnitRegistry {
             (self.try_get_dimension("[charge]") / self.try_get_dimension("[electric_potential]"))
                 .with_name("[capacitance]"),
         );
-        self.add_unit((self.try_get_unit("coulomb") / self.try_get_unit("volt")).with_name("farad"));
+        self.add_unit(
+            (self.try_get_unit("coulomb") / self.try_get_unit("volt"))
+                .unwrap()
+                .with_name("farad"),
+        );
         self.add_unit_deriv("abfarad", "[capacitance]", 1e9, "farad");
         self.add_unit_deriv("conventional_farad_90", "[capacitance]", 1.0000000000000002, "farad");
         // R_K90 / R_K
@@ -620,7 +743,11 @@ impl UnitRegistry {
             (self.try_get_dimension("[electric_potential]") * self.try_get_dimension("[time]"))
                 .with_name("[magnetic_flux]"),
         );
-        self.add_unit((self.try_get_unit("volt") * self.try_get_unit("second")).with_name("weber"));
+        self.add_unit(
+            (self.try_get_unit("volt") * self.try_get_unit("second"))
+                .unwrap()
+                .with_name("weber"),
+        );
         self.add_unit_deriv("unit_pole", "[magnetic_flux]", 1.2566370614359173e-6, "weber");
         // µ_0 * biot * centimeter
     }
@@ -628,7 +755,11 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[magnetic_flux]") / self.try_get_dimension("[current]")).with_name("[inductance]"),
         );
-        self.add_unit((self.try_get_unit("weber") / self.try_get_unit("ampere")).with_name("henry"));
+        self.add_unit(
+            (self.try_get_unit("weber") / self.try_get_unit("ampere"))
+                .unwrap()
+                .with_name("henry"),
+        );
         self.add_unit_deriv("abhenry", "[inductance]", 1e-9, "henry");
         self.add_unit_deriv("conventional_henry_90", "[inductance]", 1.0000000000000002, "henry");
         // R_K / R_K90
@@ -639,7 +770,11 @@ impl UnitRegistry {
             (self.try_get_dimension("[magnetic_flux]") / self.try_get_dimension("[area]"))
                 .with_name("[magnetic_field]"),
         );
-        self.add_unit((self.try_get_unit("weber") / self.try_get_unit("meter^2")).with_name("tesla"));
+        self.add_unit(
+            (self.try_get_unit("weber") / self.try_get_unit("meter^2"))
+                .unwrap()
+                .with_name("tesla"),
+        );
         self.add_unit_deriv("gamma", "[magnetic_field]", 1e-9, "tesla");
     }
 
@@ -656,14 +791,14 @@ impl UnitRegistry {
             (self.try_get_dimension("[current]") / self.try_get_dimension("[length]"))
                 .with_name("[magnetic_field_strength]"),
         );
-        self.add_unit(self.try_get_unit("ampere") / self.try_get_unit("meter"));
+        self.add_unit((self.try_get_unit("ampere") / self.try_get_unit("meter")).unwrap());
     }
 
     fn add_electric_dipole_moment(&mut self) {
         self.add_dimension(
             (self.try_get_dimension("[charge]") * self.try_get_dimension("[length]")).with_name("[electric_dipole]"),
         );
-        self.add_unit(self.try_get_unit("coulomb") * self.try_get_unit("meter"));
+        self.add_unit((self.try_get_unit("coulomb") * self.try_get_unit("meter")).unwrap());
         self.add_unit_deriv("debye", "[electric_dipole]", 3.3356409519815204e-30, "coulomb*meter");
         // 1e-9 / ζ * coulomb * angstrom
     }
@@ -672,7 +807,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[charge]") * self.try_get_dimension("[area]")).with_name("[electric_quadrupole]"),
         );
-        self.add_unit(self.try_get_unit("coulomb") * self.try_get_unit("meter^2"));
+        self.add_unit((self.try_get_unit("coulomb") * self.try_get_unit("meter^2")).unwrap());
         self.add_unit_deriv(
             "buckingham",
             "[electric_quadrupole]",
@@ -685,7 +820,7 @@ impl UnitRegistry {
         self.add_dimension(
             (self.try_get_dimension("[current]") * self.try_get_dimension("[area]")).with_name("[magnetic_dipole]"),
         );
-        self.add_unit(self.try_get_unit("ampere") * self.try_get_unit("meter^2"));
+        self.add_unit((self.try_get_unit("ampere") * self.try_get_unit("meter^2")).unwrap());
         self.add_unit_deriv("bohr_magneton", "[magnetic_dipole]", 9.274009994e-24, "ampere*meter^2"); // e * hbar / (2 * m_e)
         self.add_unit_deriv(
             "nuclear_magneton",
@@ -739,7 +874,7 @@ impl UnitRegistry {
         self.add_unit(self.try_get_unit("survey_mile").pow(2).with_name("square_survey_mile"));
         self.add_unit(self.try_get_unit("league").pow(2).with_name("square_league"));
         self.add_unit_deriv("acre", "[area]", 10., "square_chain");
-        self.add_unit(self.try_get_unit("acre") * (self.try_get_unit("survey_foot")).with_name("acre_foot"));
+        self.add_unit((self.try_get_unit("acre") * (self.try_get_unit("survey_foot")).with_name("acre_foot")).unwrap());
     }
 
     fn add_uscs_dry_volume(&mut self) {
@@ -749,7 +884,11 @@ impl UnitRegistry {
         self.add_unit_deriv("dry_gallon", "[volume]", 1.0 / 8.0, "bushel");
         self.add_unit_deriv("peck", "[volume]", 1.0 / 4.0, "bushel");
         self.add_unit_deriv("dry_barrel", "[volume]", 7056.0, "cubic_inch");
-        self.add_unit((self.try_get_unit("foot").pow(2) * self.try_get_unit("inch")).with_name("board_foot"));
+        self.add_unit(
+            (self.try_get_unit("foot").pow(2) * self.try_get_unit("inch"))
+                .unwrap()
+                .with_name("board_foot"),
+        );
     }
 
     fn add_uscs_liquid_volume(&mut self) {
@@ -785,8 +924,14 @@ impl UnitRegistry {
         self.add_unit_deriv("long_hundredweight", "[mass]", 112.0, "pound");
         self.add_unit_deriv("ton", "[mass]", 2000.0, "pound");
         self.add_unit_deriv("long_ton", "[mass]", 2240.0, "pound");
-        self.add_unit(self.try_get_unit("pound") * self.try_get_unit("second").pow(2) / self.try_get_unit("foot"));
-        self.add_unit(self.try_get_unit("pound") * self.try_get_unit("second").pow(2) / self.try_get_unit("inch"));
+        self.add_unit(
+            ((self.try_get_unit("pound") * self.try_get_unit("second").pow(2)).unwrap() / self.try_get_unit("foot"))
+                .unwrap(),
+        );
+        self.add_unit(
+            ((self.try_get_unit("pound") * self.try_get_unit("second").pow(2)).unwrap() / self.try_get_unit("inch"))
+                .unwrap(),
+        );
         // Need a way to handle constants with a physical unit
         // self.add_unit_deriv("slug", "[mass]", g0, "pound*second^2/foot");
         // self.add_unit_deriv("slinch", "[mass]", g0, "pound*second^2/inch");
@@ -797,8 +942,9 @@ impl UnitRegistry {
         // self.add_unit_deriv("force_long_ton", "[force]", g0, "long_ton");
         // self.add_unit_deriv("kip", "[force]", 1000.0, "force_pound");
         self.add_unit(
-            (self.try_get_unit("pound") * self.try_get_unit("foot") / self.try_get_unit("second").pow(2))
-                .with_name("poundal"),
+            (((self.try_get_unit("pound") * self.try_get_unit("foot")).unwrap() / self.try_get_unit("second").pow(2))
+                .unwrap())
+            .with_name("poundal"),
         );
     }
 
@@ -852,25 +998,335 @@ impl UnitRegistry {
         self.add_unit_deriv("tex_cicero", "[length]", 12.0, "tex_didot");
         self.add_unit_deriv("scaled_point", "[length]", 1.0 / 65536.0, "tex_point");
     }
+
+    /// Symbols and alternative names, mostly following pint's default definitions
+    fn add_symbols(&mut self) {
+        for (symbol, unit) in [
+            // base units
+            ("m", "meter"),
+            ("kg", "kilogram"),
+            ("s", "second"),
+            ("sec", "second"),
+            ("A", "ampere"),
+            ("amp", "ampere"),
+            ("K", "kelvin"),
+            // temperature
+            ("degC", "degree_Celsius"),
+            ("degreeC", "degree_Celsius"),
+            ("celsius", "degree_Celsius"),
+            ("°C", "degree_Celsius"),
+            ("℃", "degree_Celsius"),
+            ("degF", "degree_Fahrenheit"),
+            ("degreeF", "degree_Fahrenheit"),
+            ("fahrenheit", "degree_Fahrenheit"),
+            ("°F", "degree_Fahrenheit"),
+            ("℉", "degree_Fahrenheit"),
+            ("degR", "degree_Rankine"),
+            ("degreeR", "degree_Rankine"),
+            ("rankine", "degree_Rankine"),
+            ("°R", "degree_Rankine"),
+            ("degRe", "degree_Reaumur"),
+            ("degreeRe", "degree_Reaumur"),
+            ("reaumur", "degree_Reaumur"),
+            ("°Re", "degree_Reaumur"),
+            ("degree_Réaumur", "degree_Reaumur"),
+            ("réaumur", "degree_Reaumur"),
+            ("mol", "mole"),
+            ("cd", "candela"),
+            ("rad", "radian"),
+            // angles
+            ("deg", "degree"),
+            ("°", "degree"),
+            ("arcmin", "arcminute"),
+            ("′", "arcminute"),
+            ("arcsec", "arcsecond"),
+            ("″", "arcsecond"),
+            ("mas", "milliarcsecond"),
+            ("gon", "grade"),
+            ("sr", "steradian"),
+            ("sq_deg", "square_degree"),
+            ("cycle", "turn"),
+            ("revolution", "turn"),
+            // information and ratios
+            ("Bd", "baud"),
+            ("B", "byte"),
+            ("octet", "byte"),
+            ("%", "percent"),
+            ("‰", "permille"),
+            // length
+            ("Å", "angstrom"),
+            ("\u{212B}", "angstrom"), // angstrom sign
+            ("ångström", "angstrom"),
+            ("ly", "light_year"),
+            ("au", "astronomical_unit"),
+            ("pc", "parsec"),
+            ("nmi", "nautical_mile"),
+            ("a_0", "bohr"),
+            ("ft", "foot"),
+            ("in", "inch"),
+            ("yd", "yard"),
+            ("mi", "mile"),
+            ("th", "thou"),
+            ("pp", "point"),
+            // mass
+            ("g", "gram"),
+            ("gramme", "gram"),
+            ("t", "metric_ton"),
+            ("tonne", "metric_ton"),
+            ("u", "unified_atomic_mass_unit"),
+            ("amu", "unified_atomic_mass_unit"),
+            ("Da", "dalton"),
+            ("gr", "grain"),
+            ("ct", "carat"),
+            ("lb", "pound"),
+            ("oz", "ounce"),
+            ("st", "stone"),
+            ("cwt", "hundredweight"),
+            ("dwt", "pennyweight"),
+            ("ozt", "troy_ounce"),
+            ("lbt", "troy_pound"),
+            // time
+            ("min", "minute"),
+            ("h", "hour"),
+            ("hr", "hour"),
+            ("d", "day"),
+            ("yr", "year"),
+            // area and volume
+            ("ha", "hectare"),
+            ("b", "barn"),
+            ("ac", "acre"),
+            ("l", "liter"),
+            ("L", "liter"),
+            ("ℓ", "liter"),
+            ("cc", "cubic_centimeter"),
+            ("λ", "lambda"),
+            ("gal", "gallon"),
+            ("qt", "quart"),
+            ("pt", "pint"),
+            ("floz", "fluid_ounce"),
+            ("tsp", "teaspoon"),
+            ("tbsp", "tablespoon"),
+            ("bbl", "barrel"),
+            ("UK_gallon", "imperial_gallon"),
+            // frequency and velocity
+            ("Hz", "hertz"),
+            ("rpm", "revolutions_per_minute"),
+            ("rps", "revolutions_per_second"),
+            ("cps", "counts_per_second"),
+            ("kt", "knot"),
+            ("kn", "knot"),
+            ("mph", "mile_per_hour"),
+            ("MPH", "mile_per_hour"),
+            ("kph", "kilometer_per_hour"),
+            ("KPH", "kilometer_per_hour"),
+            ("mps", "meter_per_second"),
+            ("fps", "foot_per_second"),
+            // mechanics
+            ("N", "newton"),
+            ("dyn", "dyne"),
+            ("kgf", "force_kilogram"),
+            ("gf", "force_gram"),
+            ("J", "joule"),
+            ("Wh", "watt_hour"),
+            ("eV", "electron_volt"),
+            ("cal", "calorie"),
+            ("Btu", "british_thermal_unit"),
+            ("BTU", "british_thermal_unit"),
+            ("tTNT", "ton_TNT"),
+            ("toe", "tonne_of_oil_equivalent"),
+            ("W", "watt"),
+            ("VA", "volt_ampere"),
+            ("hp", "horsepower"),
+            ("Pa", "pascal"),
+            ("at", "technical_atmosphere"),
+            ("Torr", "torr"),
+            ("psi", "pound_force_per_square_inch"),
+            ("ksi", "kip_per_square_inch"),
+            ("mmHg", "millimeter_Hg"),
+            ("inHg", "inch_Hg"),
+            ("P", "poise"),
+            ("St", "stokes"),
+            // chemistry and radiation
+            ("kat", "katal"),
+            ("Bq", "becquerel"),
+            ("Ci", "curie"),
+            ("Gy", "gray"),
+            ("Sv", "sievert"),
+            // photometry
+            ("lm", "lumen"),
+            ("lx", "lux"),
+            ("sb", "stilb"),
+            // electromagnetism
+            ("C", "coulomb"),
+            ("Ah", "ampere_hour"),
+            ("V", "volt"),
+            ("Ω", "ohm"),
+            ("\u{2126}", "ohm"), // ohm sign
+            ("S", "siemens"),
+            ("F", "farad"),
+            ("Wb", "weber"),
+            ("H", "henry"),
+            ("T", "tesla"),
+            ("Gb", "gilbert"),
+            ("D", "debye"),
+        ] {
+            self.add_alias(symbol, unit);
+        }
+    }
+
+    fn add_all_spelling_variants(&mut self) {
+        let mut names: Vec<String> = self.units.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            self.add_spelling_variants(&name);
+        }
+    }
 }
 
 #[allow(clippy::redundant_closure)]
-pub static REGISTRY: Lazy<UnitRegistry> = Lazy::new(|| UnitRegistry::new_with_definitions());
+pub static REGISTRY: Lazy<RwLock<UnitRegistry>> = Lazy::new(|| RwLock::new(UnitRegistry::new_with_definitions()));
+
+/// Shared access to the global registry.
+///
+/// Keep the guard only for the duration of a lookup: holding it while calling code that locks the registry again
+/// can deadlock when a definition from Python is waiting for the write lock
+pub fn registry() -> RwLockReadGuard<'static, UnitRegistry> {
+    // definitions are only added after they have been validated, so a panic can't leave the registry half updated
+    REGISTRY.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Exclusive access to the global registry, to define new units at runtime
+pub fn registry_mut() -> RwLockWriteGuard<'static, UnitRegistry> {
+    REGISTRY.write().unwrap_or_else(PoisonError::into_inner)
+}
 
 #[cfg(test)]
 mod test {
     use is_close::is_close;
 
-    use super::REGISTRY;
+    use super::registry;
 
     #[test]
     fn test_new_definitions() {
-        assert_eq!(REGISTRY.try_get_unit("meter").name(), "meter");
+        assert_eq!(registry().try_get_unit("meter").name(), "meter");
     }
 
     #[test]
     fn test_meter_to_foot() {
-        let conv_factor = REGISTRY.convert("foot".to_string(), "meter".to_string()).unwrap();
+        let conv_factor = registry().convert("foot".to_string(), "meter".to_string()).unwrap();
         assert!(is_close!(conv_factor, 0.3048));
     }
+
+    #[test]
+    fn test_prefixed_units() {
+        let cases = [
+            ("kilometer", "meter", 1e3),
+            ("millisecond", "second", 1e-3),
+            ("megawatt", "watt", 1e6),
+            ("microgram", "kilogram", 1e-9),
+            ("gigawatt_hour", "joule", 3.6e12),
+            ("kibibyte", "bit", 8192.0),
+            ("exbibyte", "byte", 2f64.powi(60)),
+            ("quettameter", "yoctometer", 1e54),
+        ];
+        for (from, to, expected) in cases {
+            let conv_factor = registry().convert(from.to_string(), to.to_string()).unwrap();
+            assert!(is_close!(conv_factor, expected), "{} -> {}: {}", from, to, conv_factor);
+        }
+    }
+
+    #[test]
+    fn test_unknown_unit() {
+        assert!(registry()
+            .convert("kilofoobar".to_string(), "meter".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn test_symbols_and_spelling_variants() {
+        let cases = [
+            ("m", "meter", 1.0),
+            ("metres", "meter", 1.0),
+            ("kilometres", "m", 1e3),
+            ("km", "m", 1e3),
+            ("µm", "m", 1e-6),
+            ("μm", "m", 1e-6),
+            ("um", "m", 1e-6),
+            ("feet", "inches", 12.0),
+            ("kWh", "J", 3.6e6),
+            ("MWh", "kWh", 1e3),
+            ("GiB", "MiB", 1024.0),
+            ("litres", "mL", 1e3),
+            ("Ω", "ohm", 1.0),
+            ("kΩ", "ohm", 1e3),
+            ("°", "radian", std::f64::consts::PI / 180.0),
+            ("h", "min", 60.0),
+            ("centuries", "years", 100.0),
+            ("miles_per_hour", "mph", 1.0),
+            ("ms", "s", 1e-3),
+            ("Pa", "hPa", 1e-2),
+        ];
+        for (from, to, expected) in cases {
+            let conv_factor = registry().convert(from.to_string(), to.to_string()).unwrap();
+            assert!(is_close!(conv_factor, expected), "{} -> {}: {}", from, to, conv_factor);
+        }
+    }
+
+    #[test]
+    fn test_compound_units() {
+        let cases = [
+            ("m/s", "km/h", 3.6),
+            ("km/h", "mph", 1.0 / 1.609344),
+            ("N*m", "J", 1.0),
+            ("kg*m^2/s^2", "J", 1.0),
+            ("kWh", "W*s", 3.6e6),
+            ("g/cm^3", "kg/m^3", 1e3),
+            ("L", "cm^3", 1e3),
+            ("Hz", "1/s", 1.0),
+            ("km^(1/2)", "m^0.5", 1e3f64.sqrt()),
+            ("dimensionless", "", 1.0),
+        ];
+        for (from, to, expected) in cases {
+            let conv_factor = registry().convert(from.to_string(), to.to_string()).unwrap();
+            assert!(is_close!(conv_factor, expected), "{} -> {}: {}", from, to, conv_factor);
+        }
+    }
+
+    #[test]
+    fn test_compound_units_different_dimensions() {
+        assert!(registry().convert("m/s".to_string(), "m*s".to_string()).is_err());
+        assert!(registry().convert("J".to_string(), "W".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_temperature_units() {
+        let cases = [
+            ("degC", "K", 0.0, 273.15),
+            ("°C", "degF", 100.0, 212.0),
+            ("℉", "°C", 32.0, 0.0),
+            ("degF", "K", -459.67, 0.0),
+            ("degR", "degF", 491.67, 32.0),
+            ("degRe", "degC", 80.0, 100.0),
+            ("K", "degC", 0.0, -273.15),
+            ("mK", "K", 1.0, 1e-3),
+            // differences convert without the offset
+            ("delta_degF", "delta_degC", 9.0, 5.0),
+            ("delta_degree_Celsius", "K", 1.0, 1.0),
+            ("delta_celsius", "delta_degC", 1.0, 1.0),
+        ];
+        for (from, to, value, expected) in cases {
+            let (factor, offset) = registry()
+                .convert_between(&from.parse().unwrap(), &to.parse().unwrap())
+                .unwrap();
+            let converted = value * factor + offset;
+            assert!(
+                is_close!(converted, expected, abs_tol = 1e-9),
+                "{} {} -> {}: {}",
+                value,
+                from,
+                to,
+                converted
+            );
+        }
+    }
 }
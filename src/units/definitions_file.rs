@@ -0,0 +1,496 @@
+//! Loader for unit definitions in pint's text format, so that the registry can be extended without recompiling.
+//!
+//! Supported lines (`#` starts a comment, `_` is an empty symbol):
+//!
+//! ```text
+//! kilo- = 1e3 = k-                                      # prefix, with symbol and aliases
+//! meter = [length] = m = metre                          # base unit of a new base dimension
+//! radian = [] = rad                                     # dimensionless base unit
+//! [velocity] = [length] / [time]                        # derived dimension
+//! inch = 2.54 * centimeter = in                         # unit, with symbol and aliases
+//! degree_Celsius = kelvin; offset: 273.15 = °C = degC   # offset unit
+//! @alias meter = metro                                  # extra aliases for an existing unit
+//! ```
+//!
+//! Definitions in `@group` blocks are loaded as top level definitions, while `@defaults`, `@system` and
+//! `@context` blocks are skipped. `@import` and logarithmic units are not supported.
+//!
+//! `define_dimension` and `define_unit` are the equivalent of single definition lines, for definitions from code.
+use anyhow::{bail, Context, Result};
+
+use super::conversion::{Dimension, UnitRegistry};
+use super::parse_unit_expr;
+
+/// Blocks that are skipped until their `@end`
+const SKIPPED_BLOCKS: [&str; 3] = ["@defaults", "@system", "@context"];
+
+impl UnitRegistry {
+    /// A registry with only the units of a pint definitions file
+    #[cfg(test)]
+    pub fn from_definitions(text: &str) -> Result<Self> {
+        let mut registry = Self::new();
+        registry.load_definitions(text, false)?;
+        Ok(registry)
+    }
+
+    /// Load the definitions of a pint definitions file. Existing units, aliases, prefixes and dimensions are only
+    /// redefined with `replace`, as in `define_unit`. As in pint, units can be used before they are defined, failing
+    /// definitions are retried until no more definitions can be loaded
+    pub fn load_definitions(&mut self, text: &str, replace: bool) -> Result<()> {
+        let mut in_group = false;
+        let mut skipping = false;
+        let mut definitions = vec![];
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if skipping {
+                skipping = line != "@end";
+                continue;
+            }
+            let directive = line
+                .split(|c: char| c.is_whitespace() || c == '(')
+                .next()
+                .unwrap_or_default();
+            match directive {
+                "@end" if in_group => in_group = false,
+                "@group" if !in_group => in_group = true,
+                directive if SKIPPED_BLOCKS.contains(&directive) => skipping = true,
+                "@alias" => definitions.push((idx, line)),
+                directive if directive.starts_with('@') => {
+                    bail!(
+                        "Invalid unit definition at line {}: unsupported directive {}",
+                        idx + 1,
+                        directive
+                    )
+                },
+                _ => definitions.push((idx, line)),
+            }
+        }
+        if in_group || skipping {
+            bail!("Invalid unit definitions: block without @end");
+        }
+
+        let mut new_units = vec![];
+        loop {
+            let n_pending = definitions.len();
+            let mut failed = vec![];
+            for (idx, line) in definitions {
+                match self.load_line(line, replace) {
+                    Ok(unit) => new_units.extend(unit),
+                    Err(error) => failed.push((idx, line, error)),
+                }
+            }
+            if failed.is_empty() {
+                break;
+            }
+            if failed.len() == n_pending {
+                let (idx, line, error) = failed.swap_remove(0);
+                return Err(error.context(format!("Invalid unit definition at line {}: '{}'", idx + 1, line)));
+            }
+            definitions = failed.into_iter().map(|(idx, line, _)| (idx, line)).collect();
+        }
+        for name in new_units {
+            self.add_spelling_variants(&name);
+        }
+        self.add_delta_units();
+        Ok(())
+    }
+
+    /// Define a base dimension (e.g. `length`) or, with a definition, a derived one (e.g. `[length] / [time]`).
+    /// Existing dimensions are only redefined with `replace`, units keep the dimension they were defined with
+    pub fn define_dimension(&mut self, name: &str, definition: Option<&str>, replace: bool) -> Result<()> {
+        let name = format!("[{}]", name.trim_start_matches('[').trim_end_matches(']'));
+        check_redefinition("dimension", &name, self.dimensions.contains_key(&name), replace)?;
+        match definition {
+            Some(definition) => self.load_derived_dimension(&name, definition),
+            None => {
+                self.add_dimension_simple(&name);
+                Ok(())
+            },
+        }
+    }
+
+    /// Define a unit as `value * factor + offset` in `base_unit` (a unit expression, e.g. `meter / second`), or
+    /// without a base unit as the base unit of `dimension`. When both are given, their dimensions must match.
+    /// Existing units (including aliases and prefixed units) are only redefined with `replace`, as for dimensions
+    #[allow(clippy::too_many_arguments)]
+    pub fn define_unit(
+        &mut self,
+        name: &str,
+        dimension: Option<&str>,
+        factor: f64,
+        base_unit: Option<&str>,
+        offset: Option<f64>,
+        aliases: &[&str],
+        replace: bool,
+    ) -> Result<()> {
+        check_redefinition("unit", name, self.get_unit(name).is_ok(), replace)?;
+        match (base_unit, dimension) {
+            (Some(base_unit), dimension) => {
+                let parsed = parse_unit_expr(base_unit)?;
+                let base_unit = self.resolve(&parsed.units)?;
+                if let Some(dimension) = dimension {
+                    let dimension = self.get_dimension(&format!("[{}]", dimension.trim_matches(['[', ']'])))?;
+                    if !dimension.is_compatible(base_unit.dimension()) {
+                        bail!(
+                            "unit {} has dimension {:?}, but its base unit has {:?}",
+                            name,
+                            dimension,
+                            base_unit.dimension()
+                        );
+                    }
+                }
+                self.add_unit_relative_to(name, factor * parsed.factor, offset, &base_unit)?;
+            },
+            (None, Some(dimension)) => {
+                if factor != 1.0 || offset.is_some() {
+                    bail!(
+                        "base units can't have a factor or an offset, pass a base unit to define {}",
+                        name
+                    );
+                }
+                let dimension = format!("[{}]", dimension.trim_matches(['[', ']']));
+                self.get_dimension(&dimension)?;
+                self.add_unit_simple(name, &dimension);
+            },
+            (None, None) => bail!("unit {} needs a dimension or a base unit", name),
+        }
+        for alias in aliases {
+            self.add_alias(alias, name);
+        }
+        self.add_spelling_variants(name);
+        self.add_delta_units();
+        Ok(())
+    }
+
+    fn load_line(&mut self, line: &str, replace: bool) -> Result<Option<String>> {
+        match line.strip_prefix("@alias") {
+            Some(alias) => self.load_alias(alias, replace).map(|_| None),
+            None => self.load_definition(line, replace),
+        }
+    }
+
+    /// Whether `name` is a unit or an alias, without prefixed units which pint definitions can define explicitly
+    fn is_unit_name(&self, name: &str) -> bool {
+        self.units.contains_key(name) || self.aliases.contains_key(name)
+    }
+
+    /// `@alias meter = metro = metr`
+    fn load_alias(&mut self, line: &str, replace: bool) -> Result<()> {
+        let mut parts = line.split('=').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        if !self.units.contains_key(name) {
+            bail!("cannot add aliases to unknown unit {}", name);
+        }
+        let aliases: Vec<&str> = parts.filter(|alias| *alias != "_").collect();
+        for alias in &aliases {
+            check_redefinition("unit", alias, self.is_unit_name(alias), replace)?;
+        }
+        for alias in aliases {
+            self.add_alias(alias, name);
+        }
+        Ok(())
+    }
+
+    /// Load a prefix, dimension or unit definition, returning the name of the new unit. Redefinitions are checked
+    /// before changing the registry, so that failing definitions can be retried
+    fn load_definition(&mut self, line: &str, replace: bool) -> Result<Option<String>> {
+        let parts: Vec<&str> = line.split('=').map(str::trim).collect();
+        let (name, value, aliases) = match parts.as_slice() {
+            [name, value, aliases @ ..] if !name.is_empty() && !value.is_empty() => (*name, *value, aliases),
+            _ => bail!("expected `name = definition`"),
+        };
+        let aliases: Vec<&str> = aliases.iter().copied().filter(|alias| *alias != "_").collect();
+        if let Some(prefix) = name.strip_suffix('-') {
+            let aliases = aliases
+                .iter()
+                .map(|alias| alias.strip_suffix('-').context("prefix aliases must end with '-'"))
+                .collect::<Result<Vec<_>>>()?;
+            for prefix in aliases.iter().chain([&prefix]) {
+                check_redefinition("prefix", prefix, self.prefixes.contains_key(*prefix), replace)?;
+            }
+            self.add_prefix(prefix, parse_number(value)?);
+            for alias in aliases {
+                self.add_prefix_alias(alias, prefix);
+            }
+            return Ok(None);
+        }
+        if name.starts_with('[') {
+            if !aliases.is_empty() {
+                bail!("dimensions cannot have aliases");
+            }
+            check_redefinition("dimension", name, self.dimensions.contains_key(name), replace)?;
+            self.load_derived_dimension(name, value)?;
+            return Ok(None);
+        }
+        for name in aliases.iter().chain([&name]) {
+            check_redefinition("unit", name, self.is_unit_name(name), replace)?;
+        }
+
+        let (expr, modifiers) = value.split_once(';').unwrap_or((value, ""));
+        let expr = expr.trim();
+        if expr.starts_with('[') {
+            if !modifiers.is_empty() {
+                bail!("base units cannot have modifiers");
+            }
+            self.load_base_unit(name, expr)?;
+        } else {
+            let mut offset = None;
+            for modifier in modifiers.split(';').map(str::trim).filter(|m| !m.is_empty()) {
+                match modifier.split_once(':').map(|(key, value)| (key.trim(), value)) {
+                    Some(("offset", value)) => offset = Some(parse_number(value)?),
+                    Some(("logbase" | "logfactor", _)) => bail!("logarithmic units are not supported"),
+                    _ => bail!("unknown modifier '{}'", modifier),
+                }
+            }
+            let parsed = parse_unit_expr(expr)?;
+            let base_unit = self.resolve(&parsed.units)?;
+            self.add_unit_relative_to(name, parsed.factor, offset, &base_unit)?;
+        }
+        for alias in aliases {
+            self.add_alias(alias, name);
+        }
+        Ok(Some(name.to_string()))
+    }
+
+    /// `meter = [length]` (defines the `[length]` base dimension if needed) or `radian = []`
+    fn load_base_unit(&mut self, name: &str, dimension: &str) -> Result<()> {
+        let inner = dimension.strip_prefix('[').and_then(|dim| dim.strip_suffix(']'));
+        let dimension = match inner {
+            Some("") => "[dimensionless]",
+            Some(inner) if !inner.contains(['[', ']', '*', '/', ' ']) => dimension,
+            _ => bail!("base units must be defined by a single dimension, e.g. `meter = [length]`"),
+        };
+        if !self.dimensions.contains_key(dimension) {
+            if dimension == "[dimensionless]" {
+                self.add_dimension(Dimension::new(dimension, vec![]));
+            } else {
+                self.add_dimension_simple(dimension);
+            }
+        }
+        self.add_unit_simple(name, dimension);
+        Ok(())
+    }
+
+    /// `[velocity] = [length] / [time]`, all the dimensions on the right must be already defined
+    fn load_derived_dimension(&mut self, name: &str, value: &str) -> Result<()> {
+        // dimensions are parsed as unit expressions without the brackets
+        let parsed = parse_unit_expr(&value.replace(['[', ']'], ""))?;
+        if parsed.factor != 1.0 {
+            bail!("dimensions cannot have a numeric factor");
+        }
+        let dimension = parsed
+            .units
+            .units
+            .iter()
+            .try_fold(Dimension::new(name, vec![]), |acc, unit| {
+                Ok::<_, anyhow::Error>(acc * self.get_dimension(&format!("[{}]", unit.name))?.pow_rat(unit.power))
+            })?;
+        self.add_dimension(dimension.with_name(name));
+        Ok(())
+    }
+}
+
+/// Redefinitions fail unless they are explicitly allowed, so that a typo can't silently shadow e.g. `m`
+fn check_redefinition(kind: &str, name: &str, defined: bool, replace: bool) -> Result<()> {
+    if defined && !replace {
+        bail!("{} {} is already defined, use replace=True to redefine it", kind, name);
+    }
+    Ok(())
+}
+
+/// A dimensionless expression, e.g. `1e-3`, `2**10` or pint's offsets like `233.15 + 200 / 9`
+fn parse_number(expr: &str) -> Result<f64> {
+    expr.split('+').try_fold(0.0, |acc, term| {
+        let parsed = parse_unit_expr(term.trim())?;
+        if !parsed.units.units.is_empty() {
+            bail!("expected a number, got '{}'", expr.trim());
+        }
+        Ok(acc + parsed.factor)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use super::*;
+
+    const DEFINITIONS: &str = "
+        # prefixes
+        milli- = 1e-3 = m-
+        centi- = 1e-2 = c-
+        kilo- = 1e3 = k-
+        kibi- = 2**10 = Ki-
+
+        meter = [length] = m = metre
+        second = [time] = s = sec
+        kelvin = [temperature] = K
+        radian = [] = rad
+        [velocity] = [length] / [time]
+
+        @group USCSLengthInternational
+            inch = 2.54 * centimeter = in = _ = international_inch
+            foot = 12 * inch = ft
+        @end
+
+        @defaults
+            system = mks
+        @end
+
+        knot = 1852 * meter / hour = kt
+        hour = 3600 * second = h
+        pi = 3.1415926535897932384626433832795028841971693993751 = π
+        degree = pi / 180 * radian = deg
+        degree_Celsius = kelvin; offset: 273.15 = °C = degC
+        degree_Fahrenheit = 5 / 9 * kelvin; offset: 233.15 + 200 / 9 = °F = degF
+        @alias meter = metro
+    ";
+
+    #[test]
+    fn test_load_definitions() {
+        let registry = UnitRegistry::from_definitions(DEFINITIONS).unwrap();
+        let cases = [
+            ("ft", "m", 0.3048),
+            ("feet", "km", 0.3048e-3),
+            ("metro", "inches", 1.0 / 0.0254),
+            ("deg", "rad", std::f64::consts::PI / 180.0),
+            ("h", "s", 3600.0),
+            ("m/s", "km/h", 3.6),
+            ("international_inch", "mm", 25.4),
+        ];
+        for (from, to, expected) in cases {
+            let (factor, offset) = registry
+                .convert_between(&from.parse().unwrap(), &to.parse().unwrap())
+                .unwrap();
+            assert!(is_close!(factor, expected), "{} -> {}: {}", from, to, factor);
+            assert_eq!(offset, 0.0);
+        }
+        let (factor, offset) = registry
+            .convert_between(&"degF".parse().unwrap(), &"degC".parse().unwrap())
+            .unwrap();
+        assert!(is_close!(212.0 * factor + offset, 100.0));
+        assert!(registry.get_dimension("[velocity]").is_ok());
+        assert!(registry.get_unit("delta_degC").is_ok());
+        assert_eq!(registry.prefixes.get("Ki"), Some(&1024.0));
+    }
+
+    #[test]
+    fn test_define_dimension_and_unit() {
+        let mut registry =
+            UnitRegistry::from_definitions("kilo- = 1e3 = k-\nmeter = [length]\nsecond = [time]").unwrap();
+        registry.define_dimension("currency", None, false).unwrap();
+        registry
+            .define_dimension("price_per_length", Some("[currency] / [length]"), false)
+            .unwrap();
+        assert!(registry.define_dimension("length", None, false).is_err());
+
+        registry
+            .define_unit("euro", Some("currency"), 1.0, None, None, &["EUR", "€"], false)
+            .unwrap();
+        registry
+            .define_unit("cent", Some("[currency]"), 0.01, Some("euro"), None, &["ct"], false)
+            .unwrap();
+        registry
+            .define_unit(
+                "eur_per_km",
+                Some("price_per_length"),
+                1e-3,
+                Some("EUR/meter"),
+                None,
+                &[],
+                false,
+            )
+            .unwrap();
+        let (factor, _) = registry
+            .convert_between(&"cents/meter".parse().unwrap(), &"eur_per_km".parse().unwrap())
+            .unwrap();
+        assert!(is_close!(factor, 10.0));
+
+        registry
+            .define_dimension("price_per_length", Some("[currency] / [length] ** 2"), true)
+            .unwrap();
+        let currency = registry.get_dimension("[currency]").unwrap();
+        let length = registry.get_dimension("[length]").unwrap();
+        assert!(registry
+            .get_dimension("[price_per_length]")
+            .unwrap()
+            .is_compatible(&(currency / length.pow(2))));
+
+        assert!(registry
+            .define_unit("foo", Some("length"), 1.0, Some("euro"), None, &[], false)
+            .is_err());
+        assert!(registry
+            .define_unit("foo", Some("length"), 2.0, None, None, &[], false)
+            .is_err());
+        assert!(registry.define_unit("foo", None, 1.0, None, None, &[], false).is_err());
+        assert!(registry
+            .define_unit("foo", Some("mass"), 1.0, None, None, &[], false)
+            .is_err());
+
+        // units, aliases and prefixed units are only redefined with `replace`
+        for name in ["euro", "EUR", "kiloeuro"] {
+            let error = registry
+                .define_unit(name, None, 2.0, Some("cent"), None, &[], false)
+                .unwrap_err();
+            assert!(error.to_string().contains("already defined"), "{}", error);
+        }
+        registry
+            .define_unit("cent", None, 0.1, Some("euro"), None, &[], true)
+            .unwrap();
+        let (factor, _) = registry
+            .convert_between(&"cent".parse().unwrap(), &"euro".parse().unwrap())
+            .unwrap();
+        assert!(is_close!(factor, 0.1));
+    }
+
+    #[test]
+    fn test_load_definitions_errors() {
+        let cases = [
+            ("meter = [length]\nfoo = 3 * bar", "line 2"),
+            ("meter", "expected `name = definition`"),
+            ("[area] = [length] ** 2", "dimension [length] not found"),
+            ("meter = [length] + [time]", "single dimension"),
+            ("neper = 1 ; logbase: 2.718; logfactor: 0.5", "logarithmic units"),
+            ("@import constants_en.txt", "unsupported directive @import"),
+            ("@group foo\nmeter = [length]", "block without @end"),
+            ("@alias meter = metro", "unknown unit meter"),
+            ("kilo- = 1e3 = k", "must end with '-'"),
+            ("meter = [length]\nmeter = [length]", "unit meter is already defined"),
+            (
+                "meter = [length] = m\nminute = 60 * meter = m",
+                "unit m is already defined",
+            ),
+            (
+                "meter = [length]\n@alias meter = meter",
+                "unit meter is already defined",
+            ),
+            ("kilo- = 1e3\nkilo- = 1e3", "prefix kilo is already defined"),
+            (
+                "meter = [length]\n[area] = [length]\n[area] = [length] ** 2",
+                "dimension [area] is already defined",
+            ),
+        ];
+        for (text, expected) in cases {
+            let error = format!("{:#}", UnitRegistry::from_definitions(text).err().unwrap());
+            assert!(error.contains(expected), "{}: {}", text, error);
+        }
+    }
+
+    #[test]
+    fn test_load_definitions_replace() {
+        let mut registry =
+            UnitRegistry::from_definitions("meter = [length] = m\nfoot = 0.3 * meter = ft\n[area] = [length]").unwrap();
+        registry
+            .load_definitions("foot = 0.3048 * meter = ft\n[area] = [length] ** 2", true)
+            .unwrap();
+        let (factor, _) = registry
+            .convert_between(&"ft".parse().unwrap(), &"m".parse().unwrap())
+            .unwrap();
+        assert!(is_close!(factor, 0.3048));
+        let length = registry.get_dimension("[length]").unwrap();
+        assert!(registry.get_dimension("[area]").unwrap().is_compatible(&length.pow(2)));
+    }
+}
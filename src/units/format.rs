@@ -0,0 +1,395 @@
+//! Human readable representations of `Units`: plain (`m/s^2`), unicode (`m·s⁻²`),
+//! pint compatible (`meter / second ** 2`) and LaTeX siunitx (`\si{\metre\per\second\squared}`).
+use std::fmt;
+use std::str::FromStr;
+
+use num_rational::Rational64;
+use num_traits::{One, Signed};
+use polars::prelude::*;
+
+use super::definitions::registry;
+use super::{Unit, Units};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitFormat {
+    Plain,
+    Unicode,
+    Pint,
+    Latex,
+}
+
+impl FromStr for UnitFormat {
+    type Err = PolarsError;
+
+    fn from_str(s: &str) -> PolarsResult<Self> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(Self::Plain),
+            "unicode" => Ok(Self::Unicode),
+            "pint" => Ok(Self::Pint),
+            "latex" | "siunitx" => Ok(Self::Latex),
+            _ => {
+                polars_bail!(ComputeError: "Unknown unit format '{}', expected one of 'plain', 'unicode', 'pint' or 'latex'", s)
+            },
+        }
+    }
+}
+
+const DIMENSIONLESS: &str = "dimensionless";
+
+fn is_integer(power: &Rational64) -> bool {
+    power.denom().is_one()
+}
+
+fn power_to_f64(power: &Rational64) -> f64 {
+    *power.numer() as f64 / *power.denom() as f64
+}
+
+/// `^2`, `^(1/2)`, or nothing for a power of 1
+fn plain_power(power: &Rational64) -> String {
+    if power.is_one() {
+        String::new()
+    } else if is_integer(power) {
+        format!("^{}", power.numer())
+    } else {
+        format!("^({}/{})", power.numer(), power.denom())
+    }
+}
+
+fn to_superscript(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '0' => '⁰',
+            '1' => '¹',
+            '2' => '²',
+            '3' => '³',
+            '4' => '⁴',
+            '5' => '⁵',
+            '6' => '⁶',
+            '7' => '⁷',
+            '8' => '⁸',
+            '9' => '⁹',
+            '-' => '⁻',
+            '/' => 'ᐟ',
+            c => c,
+        })
+        .collect()
+}
+
+fn unicode_power(power: &Rational64) -> String {
+    if power.is_one() {
+        String::new()
+    } else if is_integer(power) {
+        to_superscript(&power.numer().to_string())
+    } else {
+        to_superscript(&format!("{}/{}", power.numer(), power.denom()))
+    }
+}
+
+fn pint_power(power: &Rational64) -> String {
+    if power.is_one() {
+        String::new()
+    } else if is_integer(power) {
+        format!(" ** {}", power.numer())
+    } else {
+        format!(" ** {}", power_to_f64(power))
+    }
+}
+
+/// siunitx macros for common units, keyed by both name and symbol
+const SIUNITX_UNITS: &[(&[&str], &str)] = &[
+    (&["meter", "metre", "m"], "\\metre"),
+    (&["second", "s"], "\\second"),
+    (&["gram", "g"], "\\gram"),
+    (&["kilogram", "kg"], "\\kilogram"),
+    (&["ampere", "A"], "\\ampere"),
+    (&["kelvin", "K"], "\\kelvin"),
+    (&["mole", "mol"], "\\mole"),
+    (&["candela", "cd"], "\\candela"),
+    (&["radian", "rad"], "\\radian"),
+    (&["steradian", "sr"], "\\steradian"),
+    (&["hertz", "Hz"], "\\hertz"),
+    (&["newton", "N"], "\\newton"),
+    (&["pascal", "Pa"], "\\pascal"),
+    (&["joule", "J"], "\\joule"),
+    (&["watt", "W"], "\\watt"),
+    (&["coulomb", "C"], "\\coulomb"),
+    (&["volt", "V"], "\\volt"),
+    (&["farad", "F"], "\\farad"),
+    (&["ohm", "Ω"], "\\ohm"),
+    (&["siemens", "S"], "\\siemens"),
+    (&["weber", "Wb"], "\\weber"),
+    (&["tesla", "T"], "\\tesla"),
+    (&["henry", "H"], "\\henry"),
+    (&["lumen", "lm"], "\\lumen"),
+    (&["lux", "lx"], "\\lux"),
+    (&["becquerel", "Bq"], "\\becquerel"),
+    (&["gray", "Gy"], "\\gray"),
+    (&["sievert", "Sv"], "\\sievert"),
+    (&["katal", "kat"], "\\katal"),
+    (&["degree_Celsius", "degC", "°C"], "\\degreeCelsius"),
+    (&["liter", "litre", "l", "L"], "\\litre"),
+    (&["minute", "min"], "\\minute"),
+    (&["hour", "h"], "\\hour"),
+    (&["day", "d"], "\\day"),
+    (&["degree", "deg", "°"], "\\degree"),
+    (&["arcminute", "arcmin"], "\\arcminute"),
+    (&["arcsecond", "arcsec"], "\\arcsecond"),
+    (&["hectare", "ha"], "\\hectare"),
+    (&["metric_ton", "tonne", "t"], "\\tonne"),
+    (&["electron_volt", "electronvolt", "eV"], "\\electronvolt"),
+    (&["dalton", "Da"], "\\dalton"),
+    (&["astronomical_unit", "au"], "\\astronomicalunit"),
+    (&["bar"], "\\bar"),
+    (&["angstrom", "Å"], "\\angstrom"),
+    (&["percent", "%"], "\\percent"),
+    (&["bit"], "\\bit"),
+    (&["byte", "B"], "\\byte"),
+];
+
+/// siunitx prefix macros, as (name, symbol, macro)
+const SIUNITX_PREFIXES: &[(&str, &str, &str)] = &[
+    ("quecto", "q", "\\quecto"),
+    ("ronto", "r", "\\ronto"),
+    ("yocto", "y", "\\yocto"),
+    ("zepto", "z", "\\zepto"),
+    ("atto", "a", "\\atto"),
+    ("femto", "f", "\\femto"),
+    ("pico", "p", "\\pico"),
+    ("nano", "n", "\\nano"),
+    ("micro", "µ", "\\micro"),
+    ("milli", "m", "\\milli"),
+    ("centi", "c", "\\centi"),
+    ("deci", "d", "\\deci"),
+    ("deca", "da", "\\deca"),
+    ("hecto", "h", "\\hecto"),
+    ("kilo", "k", "\\kilo"),
+    ("mega", "M", "\\mega"),
+    ("giga", "G", "\\giga"),
+    ("tera", "T", "\\tera"),
+    ("peta", "P", "\\peta"),
+    ("exa", "E", "\\exa"),
+    ("zetta", "Z", "\\zetta"),
+    ("yotta", "Y", "\\yotta"),
+    ("ronna", "R", "\\ronna"),
+    ("quetta", "Q", "\\quetta"),
+];
+
+fn siunitx_unit(name: &str) -> Option<&'static str> {
+    SIUNITX_UNITS
+        .iter()
+        .find(|(names, _)| names.contains(&name))
+        .map(|(_, latex)| *latex)
+}
+
+fn siunitx_prefix(prefix: &str) -> Option<&'static str> {
+    SIUNITX_PREFIXES
+        .iter()
+        .find(|(name, symbol, _)| *name == prefix || *symbol == prefix)
+        .map(|(_, _, latex)| *latex)
+}
+
+/// siunitx representation of a unit name, e.g. `kilometer` -> `\kilo\metre`. Names are only split into a prefix and a
+/// unit when the registry resolves them as a prefixed unit, e.g. `ft` is a foot, not a femtotonne
+fn siunitx_name(name: &str) -> String {
+    if let Some(latex) = siunitx_unit(name) {
+        return latex.to_string();
+    }
+    let prefixed = registry()
+        .split_prefix(name)
+        .and_then(|(prefix, unit)| Some(format!("{}{}", siunitx_prefix(prefix)?, siunitx_unit(unit)?)));
+    prefixed.unwrap_or_else(|| format!("\\text{{{}}}", name.replace('_', "\\_")))
+}
+
+fn siunitx_power(power: &Rational64) -> String {
+    let power = power.abs();
+    if power.is_one() {
+        String::new()
+    } else if power == Rational64::from_integer(2) {
+        "\\squared".to_string()
+    } else if power == Rational64::from_integer(3) {
+        "\\cubed".to_string()
+    } else if is_integer(&power) {
+        format!("\\tothe{{{}}}", power.numer())
+    } else {
+        // siunitx only takes decimal powers
+        format!("\\tothe{{{}}}", power_to_f64(&power))
+    }
+}
+
+impl Units {
+    /// Split the units in those with a positive and negative power (the latter with the sign flipped)
+    fn split_by_sign(&self) -> (Vec<Unit>, Vec<Unit>) {
+        let numerator = self.units.iter().filter(|u| u.power.is_positive()).cloned().collect();
+        let denominator = self
+            .units
+            .iter()
+            .filter(|u| u.power.is_negative())
+            .map(|u| Unit {
+                name: u.name.clone(),
+                power: -u.power,
+            })
+            .collect();
+        (numerator, denominator)
+    }
+
+    fn format_plain(&self) -> String {
+        let (numerator, denominator) = self.split_by_sign();
+        let join = |units: &[Unit]| {
+            units
+                .iter()
+                .map(|u| format!("{}{}", u.name, plain_power(&u.power)))
+                .collect::<Vec<_>>()
+                .join("*")
+        };
+        let num = if numerator.is_empty() {
+            "1".to_string()
+        } else {
+            join(&numerator)
+        };
+        match denominator.len() {
+            0 => num,
+            1 => format!("{}/{}", num, join(&denominator)),
+            _ => format!("{}/({})", num, join(&denominator)),
+        }
+    }
+
+    fn format_unicode(&self) -> String {
+        let (numerator, denominator) = self.split_by_sign();
+        numerator
+            .iter()
+            .map(|u| format!("{}{}", u.name, unicode_power(&u.power)))
+            .chain(
+                denominator
+                    .iter()
+                    .map(|u| format!("{}{}", u.name, unicode_power(&-u.power))),
+            )
+            .collect::<Vec<_>>()
+            .join("·")
+    }
+
+    fn format_pint(&self) -> String {
+        let (numerator, denominator) = self.split_by_sign();
+        let num = if numerator.is_empty() {
+            "1".to_string()
+        } else {
+            numerator
+                .iter()
+                .map(|u| format!("{}{}", u.name, pint_power(&u.power)))
+                .collect::<Vec<_>>()
+                .join(" * ")
+        };
+        denominator
+            .iter()
+            .fold(num, |acc, u| format!("{} / {}{}", acc, u.name, pint_power(&u.power)))
+    }
+
+    fn format_latex(&self) -> String {
+        let (numerator, denominator) = self.split_by_sign();
+        let num = numerator
+            .iter()
+            .map(|u| format!("{}{}", siunitx_name(&u.name), siunitx_power(&u.power)));
+        let denom = denominator
+            .iter()
+            .map(|u| format!("\\per{}{}", siunitx_name(&u.name), siunitx_power(&u.power)));
+        format!("\\si{{{}}}", num.chain(denom).collect::<String>())
+    }
+
+    /// Format the units as a string, fractional powers are kept exact (e.g. `s^(1/2)`), except for pint which uses floats
+    pub fn format(&self, format: UnitFormat) -> String {
+        if self.units.is_empty() && format != UnitFormat::Latex {
+            return DIMENSIONLESS.to_string();
+        }
+        match format {
+            UnitFormat::Plain => self.format_plain(),
+            UnitFormat::Unicode => self.format_unicode(),
+            UnitFormat::Pint => self.format_pint(),
+            UnitFormat::Latex => self.format_latex(),
+        }
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(UnitFormat::Plain))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn units(s: &str) -> Units {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_format_plain() {
+        assert_eq!(units("m/s^2").to_string(), "m/s^2");
+        assert_eq!(units("kg*m^2/s^2").to_string(), "kg*m^2/s^2");
+        assert_eq!(units("kg/(m*s^2)").to_string(), "kg/(m*s^2)");
+        assert_eq!(units("1/s").to_string(), "1/s");
+        assert_eq!(units("s^(1/2)").to_string(), "s^(1/2)");
+        assert_eq!(units("s^(-1/2)").to_string(), "1/s^(1/2)");
+        assert_eq!(units("").to_string(), "dimensionless");
+    }
+
+    #[test]
+    fn test_format_unicode() {
+        assert_eq!(units("m/s^2").format(UnitFormat::Unicode), "m·s⁻²");
+        assert_eq!(units("N*m").format(UnitFormat::Unicode), "N·m");
+        assert_eq!(units("s^(-1/2)").format(UnitFormat::Unicode), "s⁻¹ᐟ²");
+    }
+
+    #[test]
+    fn test_format_pint() {
+        assert_eq!(units("meter/second^2").format(UnitFormat::Pint), "meter / second ** 2");
+        assert_eq!(
+            units("kilogram/(meter*second^2)").format(UnitFormat::Pint),
+            "kilogram / meter / second ** 2"
+        );
+        assert_eq!(units("second^-1").format(UnitFormat::Pint), "1 / second");
+        assert_eq!(
+            units("meter^(3/4)*second^(1/2)").format(UnitFormat::Pint),
+            "meter ** 0.75 * second ** 0.5"
+        );
+    }
+
+    #[test]
+    fn test_format_latex() {
+        assert_eq!(
+            units("meter/second^2").format(UnitFormat::Latex),
+            "\\si{\\metre\\per\\second\\squared}"
+        );
+        assert_eq!(
+            units("km/h").format(UnitFormat::Latex),
+            "\\si{\\kilo\\metre\\per\\hour}"
+        );
+        assert_eq!(
+            units("kilogram*meter^4*s^(1/2)").format(UnitFormat::Latex),
+            "\\si{\\kilogram\\metre\\tothe{4}\\second\\tothe{0.5}}"
+        );
+        assert_eq!(units("m^(1/2)").format(UnitFormat::Latex), "\\si{\\metre\\tothe{0.5}}");
+        assert_eq!(
+            units("m^(1/3)").format(UnitFormat::Latex),
+            "\\si{\\metre\\tothe{0.3333333333333333}}"
+        );
+        // symbols of units are not split into a prefix and a unit (`f` and `t`, `y` and `d`)
+        assert_eq!(units("ft").format(UnitFormat::Latex), "\\si{\\text{ft}}");
+        assert_eq!(units("yd^2").format(UnitFormat::Latex), "\\si{\\text{yd}\\squared}");
+        assert_eq!(units("ms").format(UnitFormat::Latex), "\\si{\\milli\\second}");
+        assert_eq!(
+            units("foot_pound").format(UnitFormat::Latex),
+            "\\si{\\text{foot\\_pound}}"
+        );
+    }
+
+    #[test]
+    fn test_format_roundtrip() {
+        for s in ["kg*m/s^2", "m^(1/2)/s^3", "1/(A*s)", "cd"] {
+            let u = units(s);
+            for format in [UnitFormat::Plain, UnitFormat::Unicode, UnitFormat::Pint] {
+                assert_eq!(units(&u.format(format)), u, "roundtrip of {} in {:?}", s, format);
+            }
+        }
+    }
+}
@@ -0,0 +1,528 @@
+//! Parser for unit expressions, e.g. `kg*m/s^2`, `m**2 / s`, `N·m`, `s^(1/2)` or pint's `meter / second ** 2`.
+//!
+//! Grammar (whitespace between terms is an implicit multiplication, as in pint):
+//!
+//! ```text
+//! expr     := term (('*' | '·' | '/' | <implicit>) term)*
+//! term     := atom (('^' | '**') exponent | superscript)?
+//! exponent := sign? (number | '(' sign? number ('/' number)? ')')
+//! atom     := name | number | '(' expr ')'
+//! ```
+use num_rational::Rational64;
+use polars::prelude::*;
+
+use super::{rational_from_f64, Units};
+
+/// Max denominator used when a decimal exponent (e.g. pint's `second ** 0.3333333333333333`) is turned into a rational
+const MAX_EXPONENT_DENOM: i64 = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(String),
+    Superscript(String),
+    Mul,
+    Div,
+    Pow,
+    Plus,
+    Minus,
+    LParen,
+    RParen,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Name(name) => format!("'{}'", name),
+            Token::Number(number) => format!("'{}'", number),
+            Token::Superscript(sup) => format!("superscript '{}'", sup),
+            Token::Mul => "'*'".to_string(),
+            Token::Div => "'/'".to_string(),
+            Token::Pow => "'^'".to_string(),
+            Token::Plus => "'+'".to_string(),
+            Token::Minus => "'-'".to_string(),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+        }
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || matches!(c, '_' | '°' | '%' | '‰' | 'Å' | '℃' | '℉')
+}
+
+fn is_name_continue(c: char) -> bool {
+    (c.is_alphanumeric() && superscript_to_ascii(c).is_none()) || c == '_'
+}
+
+/// Maps unicode superscripts to their ascii counterpart, `ᐟ` is used as a fraction slash (e.g. `s¹ᐟ²`)
+fn superscript_to_ascii(c: char) -> Option<char> {
+    Some(match c {
+        '⁰' => '0',
+        '¹' => '1',
+        '²' => '2',
+        '³' => '3',
+        '⁴' => '4',
+        '⁵' => '5',
+        '⁶' => '6',
+        '⁷' => '7',
+        '⁸' => '8',
+        '⁹' => '9',
+        '⁻' => '-',
+        '⁺' => '+',
+        'ᐟ' => '/',
+        _ => return None,
+    })
+}
+
+/// A parsed unit expression: a numeric scale factor times a product of units
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitExpr {
+    pub factor: f64,
+    pub units: Units,
+}
+
+impl UnitExpr {
+    fn dimensionless(factor: f64) -> Self {
+        Self {
+            factor,
+            units: Units { units: vec![] },
+        }
+    }
+
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            factor: self.factor * rhs.factor,
+            units: self.units.multiply(&rhs.units),
+        }
+    }
+
+    fn div(self, rhs: Self) -> Self {
+        Self {
+            factor: self.factor / rhs.factor,
+            units: self.units.divide(&rhs.units),
+        }
+    }
+
+    fn pow(self, exp: Rational64) -> Self {
+        let exp_f64 = *exp.numer() as f64 / *exp.denom() as f64;
+        Self {
+            factor: self.factor.powf(exp_f64),
+            units: self.units.pow_rat(exp),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    /// tokens with their (char) position in the input
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> PolarsResult<Self> {
+        let mut parser = Self {
+            input,
+            tokens: vec![],
+            pos: 0,
+        };
+        parser.tokenize()?;
+        Ok(parser)
+    }
+
+    fn error<T>(&self, msg: &str, pos: usize) -> PolarsResult<T> {
+        polars_bail!(ComputeError: "Invalid unit expression '{}': {} at position {}", self.input, msg, pos)
+    }
+
+    fn tokenize(&mut self) -> PolarsResult<()> {
+        let chars: Vec<char> = self.input.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let start = i;
+            let token = match c {
+                c if c.is_whitespace() => {
+                    i += 1;
+                    continue;
+                },
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    i += 2;
+                    Token::Pow
+                },
+                '*' | '·' | '⋅' | '×' => {
+                    i += 1;
+                    Token::Mul
+                },
+                '/' => {
+                    i += 1;
+                    Token::Div
+                },
+                '^' => {
+                    i += 1;
+                    Token::Pow
+                },
+                '+' => {
+                    i += 1;
+                    Token::Plus
+                },
+                '-' => {
+                    i += 1;
+                    Token::Minus
+                },
+                '(' => {
+                    i += 1;
+                    Token::LParen
+                },
+                ')' => {
+                    i += 1;
+                    Token::RParen
+                },
+                c if c.is_ascii_digit() || c == '.' => {
+                    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                        i += 1;
+                    }
+                    // scientific notation, e.g. 1e-3
+                    if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                        let mut j = i + 1;
+                        if j < chars.len() && (chars[j] == '-' || chars[j] == '+') {
+                            j += 1;
+                        }
+                        if j < chars.len() && chars[j].is_ascii_digit() {
+                            i = j;
+                            while i < chars.len() && chars[i].is_ascii_digit() {
+                                i += 1;
+                            }
+                        }
+                    }
+                    Token::Number(chars[start..i].iter().collect())
+                },
+                c if superscript_to_ascii(c).is_some() => {
+                    let mut sup = String::new();
+                    while let Some(ascii) = chars.get(i).and_then(|c| superscript_to_ascii(*c)) {
+                        sup.push(ascii);
+                        i += 1;
+                    }
+                    Token::Superscript(sup)
+                },
+                c if is_name_start(c) => {
+                    i += 1;
+                    while i < chars.len() && is_name_continue(chars[i]) {
+                        i += 1;
+                    }
+                    Token::Name(chars[start..i].iter().collect())
+                },
+                c => return self.error(&format!("unexpected character '{}'", c), start),
+            };
+            self.tokens.push((start, token));
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    /// Position of the current token, or the end of the input
+    fn current_pos(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(p, _)| *p)
+            .unwrap_or_else(|| self.input.chars().count())
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> PolarsResult<T> {
+        match self.peek() {
+            Some(token) => self.error(
+                &format!("expected {}, got {}", expected, token.describe()),
+                self.current_pos(),
+            ),
+            None => self.error(&format!("expected {}, got end of input", expected), self.current_pos()),
+        }
+    }
+
+    fn parse(mut self) -> PolarsResult<UnitExpr> {
+        if self.tokens.is_empty() {
+            return Ok(UnitExpr::dimensionless(1.0));
+        }
+        let expr = self.parse_expr()?;
+        if self.peek().is_some() {
+            return self.unexpected("an operator");
+        }
+        Ok(expr)
+    }
+
+    fn parse_expr(&mut self) -> PolarsResult<UnitExpr> {
+        let mut acc = self.parse_term()?;
+        loop {
+            match self.peek() {
+                Some(Token::Mul) => {
+                    self.next();
+                    acc = acc.mul(self.parse_term()?);
+                },
+                Some(Token::Div) => {
+                    self.next();
+                    acc = acc.div(self.parse_term()?);
+                },
+                // implicit multiplication, e.g. `kg m`
+                Some(Token::Name(_)) | Some(Token::Number(_)) | Some(Token::LParen) => {
+                    acc = acc.mul(self.parse_term()?);
+                },
+                _ => break,
+            }
+        }
+        Ok(acc)
+    }
+
+    fn parse_term(&mut self) -> PolarsResult<UnitExpr> {
+        let atom = self.parse_atom()?;
+        match self.peek() {
+            Some(Token::Pow) => {
+                self.next();
+                let exp = self.parse_exponent()?;
+                Ok(atom.pow(exp))
+            },
+            Some(Token::Superscript(sup)) => {
+                let (pos, sup) = (self.current_pos(), sup.clone());
+                self.next();
+                let exp = self.parse_superscript(&sup, pos)?;
+                Ok(atom.pow(exp))
+            },
+            _ => Ok(atom),
+        }
+    }
+
+    fn parse_atom(&mut self) -> PolarsResult<UnitExpr> {
+        let pos = self.current_pos();
+        match self.peek().cloned() {
+            Some(Token::Name(name)) => {
+                self.next();
+                if name == "dimensionless" {
+                    Ok(UnitExpr::dimensionless(1.0))
+                } else {
+                    Ok(UnitExpr {
+                        factor: 1.0,
+                        units: Units::new_simple(&name),
+                    })
+                }
+            },
+            Some(Token::Number(number)) => {
+                self.next();
+                match number.parse::<f64>() {
+                    Ok(factor) => Ok(UnitExpr::dimensionless(factor)),
+                    Err(_) => self.error(&format!("invalid number '{}'", number), pos),
+                }
+            },
+            Some(Token::LParen) => {
+                self.next();
+                let expr = self.parse_expr()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => {
+                        self.pos -= 1;
+                        self.unexpected("')'")
+                    },
+                }
+            },
+            _ => self.unexpected("a unit"),
+        }
+    }
+
+    fn parse_sign(&mut self) -> i64 {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.next();
+                -1
+            },
+            Some(Token::Plus) => {
+                self.next();
+                1
+            },
+            _ => 1,
+        }
+    }
+
+    fn parse_exponent(&mut self) -> PolarsResult<Rational64> {
+        let sign = self.parse_sign();
+        match self.peek() {
+            Some(Token::LParen) => {
+                self.next();
+                let sign = sign * self.parse_sign();
+                let numer = self.parse_exponent_number()?;
+                let exp = if let Some(Token::Div) = self.peek() {
+                    self.next();
+                    let pos = self.current_pos();
+                    let denom = self.parse_exponent_number()?;
+                    if *denom.numer() == 0 {
+                        return self.error("exponent denominator is zero", pos);
+                    }
+                    numer / denom
+                } else {
+                    numer
+                };
+                match self.next() {
+                    Some(Token::RParen) => Ok(exp * sign),
+                    _ => {
+                        self.pos -= 1;
+                        self.unexpected("')'")
+                    },
+                }
+            },
+            _ => Ok(self.parse_exponent_number()? * sign),
+        }
+    }
+
+    fn parse_exponent_number(&mut self) -> PolarsResult<Rational64> {
+        let pos = self.current_pos();
+        match self.peek().cloned() {
+            Some(Token::Number(number)) => {
+                self.next();
+                match Self::number_to_rational(&number) {
+                    Some(exp) => Ok(exp),
+                    None => self.error(&format!("invalid exponent '{}'", number), pos),
+                }
+            },
+            _ => self.unexpected("an exponent"),
+        }
+    }
+
+    fn number_to_rational(number: &str) -> Option<Rational64> {
+        if let Ok(n) = number.parse::<i64>() {
+            return Some(Rational64::from_integer(n));
+        }
+        rational_from_f64(number.parse::<f64>().ok()?, MAX_EXPONENT_DENOM)
+    }
+
+    fn parse_superscript(&self, sup: &str, pos: usize) -> PolarsResult<Rational64> {
+        let (sign, digits) = match sup.strip_prefix('-') {
+            Some(digits) => (-1, digits),
+            None => (1, sup.strip_prefix('+').unwrap_or(sup)),
+        };
+        let parsed = match digits.split_once('/') {
+            Some((numer, denom)) => match (numer.parse::<i64>(), denom.parse::<i64>()) {
+                (Ok(numer), Ok(denom)) if denom != 0 => Some(Rational64::new(numer, denom)),
+                _ => None,
+            },
+            None => digits.parse::<i64>().ok().map(Rational64::from_integer),
+        };
+        match parsed {
+            Some(exp) => Ok(exp * sign),
+            None => self.error(&format!("invalid superscript exponent '{}'", sup), pos),
+        }
+    }
+}
+
+/// Parse a unit expression that can contain a numeric scale factor, e.g. `1e-3 * kilogram`
+pub fn parse_unit_expr(input: &str) -> PolarsResult<UnitExpr> {
+    Parser::new(input)?.parse()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::units::Unit;
+
+    fn unit(name: &str, numer: i64, denom: i64) -> Unit {
+        Unit {
+            name: name.to_string(),
+            power: Rational64::new(numer, denom),
+        }
+    }
+
+    fn parse(input: &str) -> Vec<Unit> {
+        input.parse::<Units>().unwrap().units
+    }
+
+    #[test]
+    fn test_parse_simple() {
+        assert_eq!(parse("m"), vec![unit("m", 1, 1)]);
+        assert_eq!(parse("meter"), vec![unit("meter", 1, 1)]);
+        assert_eq!(parse(""), vec![]);
+        assert_eq!(parse("dimensionless"), vec![]);
+    }
+
+    #[test]
+    fn test_parse_compound() {
+        assert_eq!(
+            parse("kg*m/s^2"),
+            vec![unit("kg", 1, 1), unit("m", 1, 1), unit("s", -2, 1)]
+        );
+        assert_eq!(parse("m**2 / s"), vec![unit("m", 2, 1), unit("s", -1, 1)]);
+        assert_eq!(parse("N·m"), vec![unit("N", 1, 1), unit("m", 1, 1)]);
+        assert_eq!(parse("kg m"), vec![unit("kg", 1, 1), unit("m", 1, 1)]);
+        assert_eq!(parse("1/s"), vec![unit("s", -1, 1)]);
+        assert_eq!(
+            parse("kg/(m*s^2)"),
+            vec![unit("kg", 1, 1), unit("m", -1, 1), unit("s", -2, 1)]
+        );
+        assert_eq!(parse("(m/s)^2"), vec![unit("m", 2, 1), unit("s", -2, 1)]);
+    }
+
+    #[test]
+    fn test_parse_rational_powers() {
+        assert_eq!(parse("s^(1/2)"), vec![unit("s", 1, 2)]);
+        assert_eq!(parse("s^(-1/2)"), vec![unit("s", -1, 2)]);
+        assert_eq!(parse("s^-2"), vec![unit("s", -2, 1)]);
+        assert_eq!(parse("s**0.5"), vec![unit("s", 1, 2)]);
+        assert_eq!(parse("m·s⁻²"), vec![unit("m", 1, 1), unit("s", -2, 1)]);
+        assert_eq!(parse("s¹ᐟ²"), vec![unit("s", 1, 2)]);
+    }
+
+    #[test]
+    fn test_parse_pint_format() {
+        assert_eq!(
+            parse("meter / second ** 2"),
+            vec![unit("meter", 1, 1), unit("second", -2, 1)]
+        );
+        assert_eq!(
+            parse("meter ** 0.5 * second ** 0.75"),
+            vec![unit("meter", 1, 2), unit("second", 3, 4)]
+        );
+        assert_eq!(parse("1 / second ** 0.3333333333333333"), vec![unit("second", -1, 3)]);
+        assert_eq!(
+            parse("kilogram / meter / second ** 2"),
+            vec![unit("kilogram", 1, 1), unit("meter", -1, 1), unit("second", -2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_parse_unit_expr_factor() {
+        let expr = parse_unit_expr("1e-3 * kilogram").unwrap();
+        assert_eq!(expr.factor, 1e-3);
+        assert_eq!(expr.units.units, vec![unit("kilogram", 1, 1)]);
+        let expr = parse_unit_expr("10^3 m").unwrap();
+        assert_eq!(expr.factor, 1000.0);
+    }
+
+    #[test]
+    fn test_parse_scale_factor_not_a_unit() {
+        let err = "1000 * m".parse::<Units>().unwrap_err();
+        assert!(err.to_string().contains("scale factor"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = "kg*/s".parse::<Units>().unwrap_err().to_string();
+        assert!(err.contains("position 3"), "{}", err);
+        let err = "m^".parse::<Units>().unwrap_err().to_string();
+        assert!(
+            err.contains("expected an exponent, got end of input at position 2"),
+            "{}",
+            err
+        );
+        let err = "(m/s".parse::<Units>().unwrap_err().to_string();
+        assert!(err.contains("expected ')'"), "{}", err);
+        let err = "m $".parse::<Units>().unwrap_err().to_string();
+        assert!(err.contains("unexpected character '$' at position 2"), "{}", err);
+        let err = "m)".parse::<Units>().unwrap_err().to_string();
+        assert!(err.contains("expected an operator, got ')' at position 1"), "{}", err);
+        let err = "s^(1/0)".parse::<Units>().unwrap_err().to_string();
+        assert!(err.contains("denominator is zero"), "{}", err);
+    }
+}
@@ -0,0 +1,49 @@
+import polars as pl
+import pytest
+
+import polars_qt as plqt
+
+
+@pytest.fixture
+def df():
+    return pl.DataFrame({"length": [500.0, 1000.0, 1500.0]}).with_columns(
+        length=plqt.with_unit("length", "m")
+    )
+
+
+@pytest.mark.parametrize(
+    "op, expected",
+    [
+        (plqt.eq, [False, True, False]),
+        (plqt.ne, [True, False, True]),
+        (plqt.lt, [True, False, False]),
+        (plqt.le, [True, True, False]),
+        (plqt.gt, [False, False, True]),
+        (plqt.ge, [False, True, True]),
+    ],
+)
+def test_compare_with_literal(df, op, expected):
+    result = df.select(op("length", plqt.lit(1, "km")))
+    assert result["length"].to_list() == expected
+
+
+def test_filter(df):
+    result = df.filter(plqt.gt("length", plqt.lit(0.8, "km")))
+    assert result["length"].struct.field("value").to_list() == [1000.0, 1500.0]
+
+
+def test_compare_different_dimensions(df):
+    with pytest.raises(pl.exceptions.ComputeError, match="different dimensions"):
+        df.select(plqt.lt("length", plqt.lit(1, "s")))
+
+
+def test_is_between(df):
+    result = df.select(
+        plqt.is_between("length", plqt.lit(0.5, "km"), plqt.lit(1, "km"), closed="left")
+    )
+    assert result["length"].to_list() == [True, False, False]
+
+
+def test_is_close(df):
+    result = df.select(plqt.is_close("length", plqt.lit(1.0001, "km"), rel_tol=1e-3))
+    assert result["length"].to_list() == [False, True, False]
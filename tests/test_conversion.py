@@ -1,5 +1,8 @@
 import polars as pl
 import numpy as np
+import pytest
+
+import polars_qt as plqt
 
 
 def test_conversion_simple():
@@ -21,3 +24,60 @@ def test_conversion_simple():
         df["actual_foot"].struct.field("value").to_numpy(),
         df["expected_foot"].struct.field("value").to_numpy(),
     )
+
+
+def test_conversion_compound():
+    df = pl.DataFrame(
+        {"speed": pl.Series([1.0, 10.0]).qt.with_unit([("m", (1, 1)), ("s", (-1, 1))])}
+    )
+    df = df.with_columns(actual=pl.col("speed").qt.convert("km/h"))
+    assert np.allclose(df["actual"].struct.field("value").to_numpy(), [3.6, 36.0])
+
+
+def test_conversion_integer_values():
+    df = pl.DataFrame({"length": pl.Series([1, 2]).qt.with_unit([("km", (1, 1))])})
+    df = df.with_columns(actual=pl.col("length").qt.convert("m"))
+    assert df["actual"].struct.field("value").dtype == pl.Float64
+    assert df["actual"].struct.field("value").to_list() == [1000.0, 2000.0]
+
+
+def test_conversion_temperature():
+    df = pl.DataFrame(
+        {"temp": pl.Series([32.0, 212.0, -40.0]).qt.with_unit([("degF", (1, 1))])}
+    )
+    df = df.with_columns(
+        kelvin=pl.col("temp").qt.convert("K"),
+        celsius=pl.col("temp").qt.convert("degC"),
+    )
+    assert np.allclose(
+        df["kelvin"].struct.field("value").to_numpy(), [273.15, 373.15, 233.15]
+    )
+    assert np.allclose(
+        df["celsius"].struct.field("value").to_numpy(), [0.0, 100.0, -40.0]
+    )
+
+
+def test_temperature_difference():
+    df = pl.DataFrame(
+        {
+            "inlet": pl.Series([20.0, 25.0]).qt.with_unit([("degC", (1, 1))]),
+            "outlet": pl.Series([68.0, 86.0]).qt.with_unit([("degF", (1, 1))]),
+        }
+    )
+    df = df.with_columns(diff=pl.col("outlet").qt.sub(pl.col("inlet")))
+    assert np.allclose(df["diff"].struct.field("value").to_numpy(), [0.0, 9.0])
+    assert df["diff"].struct.field("unit")[0] == "delta_degF"
+
+
+def test_add_converts_right_operand():
+    df = pl.DataFrame({"a": [1.0, 2.0], "b": [500.0, 1.0]}).select(
+        total=plqt.add(plqt.with_unit("a", "km"), plqt.with_unit("b", "m"))
+    )
+    assert np.allclose(df["total"].struct.field("value").to_numpy(), [1.5, 2.001])
+    assert df["total"].struct.field("unit")[0] == "km"
+
+
+def test_add_different_dimensions():
+    df = pl.DataFrame({"a": [1.0], "b": [1.0]})
+    with pytest.raises(pl.exceptions.ComputeError, match="different dimensions"):
+        df.select(plqt.add(plqt.with_unit("a", "km"), plqt.with_unit("b", "s")))
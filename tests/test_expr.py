@@ -1,4 +1,5 @@
 import polars as pl
+import polars_qt as plqt
 import pytest
 
 
@@ -6,9 +7,21 @@ def test_series_creation():
     series = pl.Series([1, 2, 3]).qt.with_unit([("m", (1, 1))])
     to_series = pl.Series(
         [
-            {"value": 1, "unit": [{"name": "m", "power": {"numer": 1, "denom": 1}}]},
-            {"value": 2, "unit": [{"name": "m", "power": {"numer": 1, "denom": 1}}]},
-            {"value": 3, "unit": [{"name": "m", "power": {"numer": 1, "denom": 1}}]},
+            {"value": 1, "unit": "m"},
+            {"value": 2, "unit": "m"},
+            {"value": 3, "unit": "m"},
+        ]
+    )
+    assert (series == to_series).all()
+
+
+def test_series_creation_fractional_power():
+    series = pl.Series([1, 2, 3]).qt.with_unit([("m", (1, 1)), ("s", (-1, 2))])
+    to_series = pl.Series(
+        [
+            {"value": 1, "unit": "m/s^(1/2)"},
+            {"value": 2, "unit": "m/s^(1/2)"},
+            {"value": 3, "unit": "m/s^(1/2)"},
         ]
     )
     assert (series == to_series).all()
@@ -18,3 +31,123 @@ def test_series_wrong_num_type():
     with pytest.raises(ValueError) as e:
         pl.Series(["a", "b", "c"]).qt.with_unit([("m", (1, 1))])
     assert "Unit supports" in str(e.value)
+
+
+def test_with_unit_from_string():
+    df = pl.DataFrame({"a": [1.0, 2.0]}).select(a=plqt.with_unit("a", "kg*m/s^2"))
+    assert df["a"].struct.field("unit")[0] == "kg*m/s^2"
+
+
+def test_with_unit_invalid_string():
+    with pytest.raises(pl.exceptions.ComputeError) as e:
+        pl.DataFrame({"a": [1.0]}).select(plqt.with_unit("a", "kg*/s"))
+    assert "position 3" in str(e.value)
+
+
+@pytest.mark.parametrize(
+    "format, expected",
+    [
+        ("plain", "m/s^2"),
+        ("unicode", "m·s⁻²"),
+        ("pint", "m / s ** 2"),
+        ("latex", "\\si{\\metre\\per\\second\\squared}"),
+    ],
+)
+def test_format_unit(format, expected):
+    df = pl.DataFrame({"a": [1.0, 2.0]}).select(
+        a=plqt.format_unit(plqt.with_unit("a", "m/s^2"), format=format)
+    )
+    assert df["a"].to_list() == [expected, expected]
+
+
+@pytest.mark.parametrize("typed", [False, True])
+@pytest.mark.parametrize("dtype", [pl.Int32, pl.Int64, pl.Float32, pl.Float64])
+def test_schema_matches_result(dtype, typed):
+    lf = (
+        pl.LazyFrame({"a": [1, 2, 4]}, schema={"a": dtype})
+        .select(a=plqt.with_unit("a", "m", typed=typed))
+        .select(
+            a=plqt.noop("a"),
+            mean=plqt.mean("a"),
+            sum=plqt.sum("a"),
+            sqrt=plqt.sqrt("a"),
+            square=plqt.pow("a", 2),
+            diff=plqt.diff("a"),
+            product=plqt.mul("a", "a"),
+            ratio=plqt.div("a", 2),
+            cm=plqt.convert("a", "cm"),
+        )
+    )
+    assert lf.collect_schema() == lf.collect().schema
+    if not typed:
+        assert lf.collect_schema()["a"] == plqt.QuantityDtype(dtype)
+
+
+# integer values in the default layout are only converted by integer factors, see test_integer_values_stay_integers
+@pytest.mark.parametrize(
+    "dtype, typed", [(pl.Int64, True), (pl.Float64, False), (pl.Float64, True)]
+)
+def test_schema_matches_converted_operands(dtype, typed):
+    lf = (
+        pl.LazyFrame({"a": [1, 2, 4]}, schema={"a": dtype})
+        .select(
+            km=plqt.with_unit("a", "km", typed=typed),
+            m=plqt.with_unit("a", "m", typed=typed),
+            t=plqt.with_unit("a", "degC", typed=typed),
+        )
+        .select(
+            total=plqt.add("km", "m"),
+            diff=plqt.sub("t", "t"),
+            product=plqt.mul("t", "m"),
+            shifted=plqt.shift("m", 1, plqt.lit(50, "cm", typed=typed)),
+        )
+    )
+    assert lf.collect_schema() == lf.collect().schema
+
+
+def test_integer_values_stay_integers():
+    lf = pl.LazyFrame({"a": [1, 2, 4]}).select(
+        km=plqt.with_unit("a", "km"), m=plqt.with_unit("a", "m")
+    )
+    lf = lf.select(total=plqt.add("km", "km"), converted=plqt.add("m", "km"))
+    df = lf.collect()
+    assert lf.collect_schema() == df.schema
+    assert df["total"].struct.field("value").dtype == pl.Int64
+    assert df["converted"].struct.field("value").to_list() == [1001, 2002, 4004]
+
+
+def test_integer_values_truncated_by_conversion():
+    lf = pl.LazyFrame({"a": [1, 2, 4]}).select(
+        km=plqt.with_unit("a", "km"), m=plqt.with_unit("a", "m")
+    )
+    with pytest.raises(pl.exceptions.ComputeError) as e:
+        lf.select(plqt.add("km", "m")).collect()
+    assert "cast them to floats first" in str(e.value)
+    # typed quantities declare float values for converted operands when planning
+    typed = pl.LazyFrame({"a": [1, 2, 4]}).select(
+        km=plqt.with_unit("a", "km", typed=True), m=plqt.with_unit("a", "m", typed=True)
+    )
+    total = typed.select(total=plqt.add("km", "m")).collect()["total"]
+    assert total.struct.field("value").to_list() == pytest.approx([1.001, 2.002, 4.004])
+
+
+def test_typed_units_in_schema():
+    lf = pl.LazyFrame({"d": [1.0, 2.0], "t": [2.0, 4.0]}).select(
+        d=plqt.with_unit("d", "km", typed=True),
+        t=plqt.with_unit("t", "h", typed=True),
+    )
+    schema = lf.select(speed=plqt.div("d", "t")).collect_schema()
+    assert schema["speed"].fields[1].dtype == pl.Enum(["km/h"])
+
+
+def test_typed_unit_errors_when_planning():
+    lf = pl.LazyFrame({"d": [1.0], "t": [2.0]}).select(
+        d=plqt.with_unit("d", "m", typed=True),
+        t=plqt.with_unit("t", "s", typed=True),
+    )
+    # raised without running the query
+    with pytest.raises(pl.exceptions.ComputeError) as e:
+        lf.select(plqt.add("d", "t")).collect_schema()
+    assert "s to m" in str(e.value)
+    with pytest.raises(pl.exceptions.ComputeError):
+        lf.select(plqt.lt("d", plqt.lit(1, "kg", typed=True))).collect_schema()
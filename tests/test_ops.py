@@ -1,3 +1,6 @@
+from fractions import Fraction
+
+import numpy as np
 import polars as pl
 import polars_qt as plqt
 import pytest
@@ -149,18 +152,78 @@ class TestNoop:
                         {
                             "value": 3,
                             "unit": [{"name": "cm", "power": {"numer": 1, "denom": 1}}],
-                        },  # different unit
+                        },  # different unit, converted to the unit of the first row
+                    ],
+                    dtype=plqt.QuantityDtype(pl.Float64, plqt.ListUnitDType),
+                )
+            }
+        )
+        result = df.select(qt_noop=plqt.noop("qt"))["qt_noop"]
+        assert result.struct.field("value").to_list() == [1.0, 2.0, 0.03]
+        # the units of the result are stored as strings
+        assert result.struct.field("unit").to_list() == ["m"] * 3
+
+    def test_multiple_string_units(self):
+        df = pl.DataFrame(
+            {
+                "qt": pl.Series(
+                    [
+                        {"value": 1, "unit": "m"},
+                        {"value": 2, "unit": "m"},
+                        {"value": 3, "unit": "cm"},
+                    ],
+                    dtype=plqt.QuantityDtype(pl.Float64),
+                )
+            }
+        )
+        result = df.select(qt_noop=plqt.noop("qt"))["qt_noop"]
+        assert result.struct.field("value").to_list() == [1.0, 2.0, 0.03]
+        assert result.struct.field("unit").to_list() == ["m"] * 3
+
+    def test_multiple_integer_units(self):
+        df = pl.DataFrame(
+            {
+                "qt": pl.Series(
+                    [
+                        {"value": 1, "unit": "cm"},
+                        {"value": 2, "unit": "m"},
                     ],
                     dtype=plqt.QuantityDtype(pl.Int64),
                 )
             }
         )
+        # integer values stay integers, as declared in the schema
+        result = df.select(qt_noop=plqt.noop("qt"))["qt_noop"]
+        assert result.struct.field("value").to_list() == [1, 200]
+        # unless they would be truncated
+        with pytest.raises(pl.exceptions.ComputeError) as e:
+            df.select(plqt.noop(pl.col("qt").reverse()))
+        assert "cast them to floats first" in str(e.value)
+
+    def test_multiple_incompatible_units(self):
+        df = pl.DataFrame(
+            {
+                "qt": pl.Series(
+                    [
+                        {
+                            "value": 1,
+                            "unit": [{"name": "m", "power": {"numer": 1, "denom": 1}}],
+                        },
+                        {
+                            "value": 3,
+                            "unit": [{"name": "s", "power": {"numer": 1, "denom": 1}}],
+                        },
+                    ],
+                    dtype=plqt.QuantityDtype(pl.Int64, plqt.ListUnitDType),
+                )
+            }
+        )
         with pytest.raises(pl.exceptions.ComputeError) as e:
             df.with_columns(qt_noop=plqt.noop("qt"))
-        assert "Expected all units" in str(e.value)
+        assert "Expected all units to be compatible" in str(e.value)
 
 
-_test_unit = [{"name": "m", "power": {"numer": 1, "denom": 1}}]
+_test_unit = "m"
 
 
 class TestUnaryOps:
@@ -199,23 +262,266 @@ class TestUnaryOps:
     def test_abs(self):
         assert self._test_op(plqt.abs, lambda x: x.abs())
 
-    def test_sin(self):
-        assert self._test_op(plqt.sin, lambda x: x.sin())
-
-    def test_arccos(self):
-        assert self._test_op(plqt.arccos, lambda x: x.arccos())
-
     def test_mean(self):
         assert self._test_op(plqt.mean, lambda x: x.mean())
 
+    def test_var(self):
+        exp_unit = "m^2"
+        assert self._test_op(plqt.var, lambda x: x.var(), exp_unit)
+
+    def test_product(self):
+        exp_unit = "m^5"
+        assert self._test_op(plqt.product, lambda x: x.product(), exp_unit)
+
     def test_pow_int(self):
-        exp_unit = pl.lit(
-            [{"name": "m", "power": {"numer": 2, "denom": 1}}], dtype=plqt.UnitDType
-        )
+        exp_unit = "m^2"
         assert self._test_op(lambda x: plqt.pow(x, 2), lambda x: x.pow(2), exp_unit)
 
-    def test_sqrt(self):
-        exp_unit = pl.lit(
-            [{"name": "m", "power": {"numer": 1, "denom": 2}}], dtype=plqt.UnitDType
+    def test_pow_numpy_int(self):
+        exp_unit = "m^2"
+        assert self._test_op(
+            lambda x: plqt.pow(x, np.int64(2)), lambda x: x.pow(2), exp_unit
         )
+
+    def test_sqrt(self):
+        exp_unit = "m^(1/2)"
         assert self._test_op(plqt.sqrt, lambda x: x.sqrt(), exp_unit)
+
+    def test_floor(self):
+        assert self._test_op(plqt.floor, lambda x: x.floor())
+
+    def test_ceil(self):
+        assert self._test_op(plqt.ceil, lambda x: x.ceil())
+
+    def test_round(self):
+        assert self._test_op(lambda x: plqt.round(x, 1), lambda x: x.round(1))
+
+    def test_sign(self):
+        exp_unit = "dimensionless"
+        assert self._test_op(plqt.sign, lambda x: x.sign(), exp_unit)
+
+    def test_clip(self):
+        assert self._test_op(
+            lambda x: plqt.clip(x, plqt.lit(-200, "cm"), plqt.lit(3, "m")),
+            lambda x: x.clip(-2, 3),
+        )
+
+
+class TestDimensionlessOps:
+    @pytest.mark.parametrize(
+        "qt_op, np_op",
+        [
+            (plqt.exp, np.exp),
+            (plqt.expm1, np.expm1),
+            (plqt.log, np.log),
+            (plqt.log1p, np.log1p),
+            (plqt.log10, np.log10),
+            (plqt.log2, np.log2),
+        ],
+    )
+    def test_reduced_units(self, qt_op, np_op):
+        df = pl.DataFrame({"ratio": [1.0, 500.0, 2000.0]}).select(
+            ratio=qt_op(plqt.with_unit("ratio", "m/km"))
+        )
+        assert np.allclose(
+            df["ratio"].struct.field("value").to_numpy(),
+            np_op(np.array([1.0, 500.0, 2000.0]) / 1000),
+        )
+        assert df["ratio"].struct.field("unit")[0] == "dimensionless"
+
+    def test_not_dimensionless(self):
+        df = pl.DataFrame({"length": [5.0]})
+        with pytest.raises(pl.exceptions.ComputeError, match="expects a dimensionless"):
+            df.select(plqt.log(plqt.with_unit("length", "m")))
+
+
+class TestTrigOps:
+    def test_angle_units(self):
+        df = pl.DataFrame({"angle": [0.0, 30.0, 90.0]}).select(
+            sin=plqt.sin(plqt.with_unit("angle", "degree")),
+            cos=plqt.cos(plqt.with_unit("angle", "deg")),
+        )
+        assert np.allclose(df["sin"].struct.field("value").to_numpy(), [0.0, 0.5, 1.0])
+        assert np.allclose(
+            df["cos"].struct.field("value").to_numpy(), [1.0, np.sqrt(3) / 2, 0.0]
+        )
+        assert df["sin"].struct.field("unit")[0] == "dimensionless"
+
+    def test_inverse_in_radians(self):
+        df = pl.DataFrame({"ratio": [0.0, 0.5, 1.0]}).select(
+            angle=plqt.arcsin(plqt.with_unit("ratio", "dimensionless"))
+        )
+        assert np.allclose(
+            df["angle"].struct.field("value").to_numpy(), np.arcsin([0.0, 0.5, 1.0])
+        )
+        assert df["angle"].struct.field("unit")[0] == "radian"
+
+    def test_arccos(self):
+        df = pl.DataFrame({"ratio": [-1.0, 0.0, 0.5, 1.0]}).select(
+            angle=plqt.arccos(plqt.with_unit("ratio", "dimensionless"))
+        )
+        assert np.allclose(
+            df["angle"].struct.field("value").to_numpy(),
+            np.arccos([-1.0, 0.0, 0.5, 1.0]),
+        )
+        assert df["angle"].struct.field("unit").to_list() == ["radian"] * 4
+
+    def test_sin(self):
+        df = pl.DataFrame({"angle": [0.0, 1.0, -5.0]}).select(
+            sin=plqt.sin(plqt.with_unit("angle", "radian"))
+        )
+        assert np.allclose(
+            df["sin"].struct.field("value").to_numpy(), np.sin([0.0, 1.0, -5.0])
+        )
+        assert df["sin"].struct.field("unit")[0] == "dimensionless"
+
+    def test_not_an_angle(self):
+        df = pl.DataFrame({"length": [5.0]})
+        with pytest.raises(pl.exceptions.ComputeError, match="expects an angle"):
+            df.select(plqt.sin(plqt.with_unit("length", "m")))
+
+
+class TestSequenceOps:
+    @pytest.fixture
+    def df(self):
+        return pl.DataFrame({"length": [1.0, 3.0, 6.0]}).select(
+            length=plqt.with_unit("length", "m")
+        )
+
+    def test_diff(self, df):
+        result = df.select(plqt.diff("length"))["length"]
+        assert result.struct.field("value").to_list() == [None, 2.0, 3.0]
+        assert result.struct.field("unit")[1] == "m"
+
+    def test_diff_offset_unit(self):
+        df = pl.DataFrame({"t": [20.0, 25.0]}).select(
+            t=plqt.diff(plqt.with_unit("t", "degC"))
+        )
+        assert df["t"].struct.field("value").to_list() == [None, 5.0]
+        assert df["t"].struct.field("unit")[1] == "delta_degC"
+
+    def test_shift(self, df):
+        result = df.select(plqt.shift("length", 1))["length"]
+        assert result.struct.field("value").to_list() == [None, 1.0, 3.0]
+
+    def test_shift_fill_value(self, df):
+        result = df.select(plqt.shift("length", -1, plqt.lit(50, "cm")))["length"]
+        assert result.struct.field("value").to_list() == [3.0, 6.0, 0.5]
+
+    def test_cum_sum(self, df):
+        result = df.select(plqt.cum_sum("length"))["length"]
+        assert result.struct.field("value").to_list() == [1.0, 4.0, 10.0]
+
+    def test_cum_sum_offset_unit(self):
+        df = pl.DataFrame({"t": [20.0, 30.0]})
+        with pytest.raises(pl.exceptions.ComputeError, match="offset units"):
+            df.select(plqt.cum_sum(plqt.with_unit("t", "degC")))
+        result = df.select(plqt.cum_sum(plqt.with_unit("t", "delta_degC")))["t"]
+        assert result.struct.field("value").to_list() == [20.0, 50.0]
+
+    def test_cum_prod(self, df):
+        result = df.select(plqt.cum_prod("length"))["length"]
+        assert result.struct.field("value").to_list() == [1.0, 3.0, 18.0]
+        assert result.struct.field("unit").to_list() == ["m", "m^2", "m^3"]
+
+    def test_cum_count(self, df):
+        result = df.select(plqt.cum_count("length"))["length"]
+        assert result.to_list() == [1, 2, 3]
+
+    def test_pct_change(self, df):
+        result = df.select(plqt.pct_change("length"))["length"]
+        assert result.struct.field("value").to_list() == [None, 2.0, 1.0]
+        assert result.struct.field("unit")[1] == "dimensionless"
+
+    def test_arg_max_min(self):
+        df = pl.DataFrame(
+            {
+                "length": pl.Series(
+                    [
+                        {"value": 1.0, "unit": _test_unit},
+                        {
+                            "value": 500.0,
+                            "unit": "cm",
+                        },
+                        {"value": 2.0, "unit": _test_unit},
+                    ],
+                    dtype=plqt.QuantityDtype(pl.Float64),
+                )
+            }
+        ).select(
+            arg_max=plqt.arg_max("length"),
+            arg_min=plqt.arg_min("length"),
+        )
+        assert df.row(0) == (1, 0)
+
+    def test_dot(self):
+        df = pl.DataFrame({"force": [1.0, 2.0], "distance": [300.0, 400.0]}).select(
+            work=plqt.dot(
+                plqt.with_unit("force", "N"), plqt.with_unit("distance", "cm")
+            )
+        )
+        assert df["work"].struct.field("value").to_list() == [1100.0]
+        assert df["work"].struct.field("unit")[0] == "N*cm"
+
+
+class TestPlainOperands:
+    @pytest.fixture
+    def df(self):
+        return pl.DataFrame({"speed": [10.0, 20.0], "count": [1, 4]}).with_columns(
+            speed=plqt.with_unit("speed", "m/s")
+        )
+
+    def test_mul_literal(self, df):
+        result = df.select(plqt.mul("speed", 2))["speed"]
+        assert result.struct.field("value").to_list() == [20.0, 40.0]
+        assert result.struct.field("unit")[0] == df["speed"].struct.field("unit")[0]
+
+    def test_mul_column(self, df):
+        result = df.select(plqt.mul("speed", "count"))["speed"]
+        assert result.struct.field("value").to_list() == [10.0, 80.0]
+
+    def test_div_plain_by_quantity(self, df):
+        result = df.select(time=plqt.div(pl.lit(100.0), "speed"))["time"]
+        assert result.struct.field("value").to_list() == [10.0, 5.0]
+
+    def test_add_dimensionless(self):
+        df = pl.DataFrame({"ratio": [500.0]}).select(
+            ratio=plqt.add(plqt.with_unit("ratio", "m/km"), 1)
+        )
+        assert df["ratio"].struct.field("value").to_list() == [1500.0]
+
+    def test_add_not_dimensionless(self, df):
+        with pytest.raises(pl.exceptions.ComputeError, match="plain number"):
+            df.select(plqt.add("speed", 1))
+
+
+class TestPow:
+    def test_fraction(self):
+        df = pl.DataFrame({"volume": [8.0, 27.0]}).select(
+            side=plqt.pow(plqt.with_unit("volume", "m^3"), Fraction(1, 3))
+        )
+        assert np.allclose(df["side"].struct.field("value").to_numpy(), [2.0, 3.0])
+        assert df["side"].struct.field("unit")[0] == "m"
+
+    def test_float_snapped(self):
+        df = pl.DataFrame({"length": [4.0]}).select(
+            length=plqt.pow(plqt.with_unit("length", "m"), 0.1)
+        )
+        assert df["length"].struct.field("unit")[0] == "m^(1/10)"
+
+    def test_float_max_denom(self):
+        df = pl.DataFrame({"length": [4.0]}).select(
+            length=plqt.pow(plqt.with_unit("length", "m"), 0.333, max_denom=10)
+        )
+        assert df["length"].struct.field("unit")[0] == "m^(1/3)"
+
+    def test_column_exponent(self):
+        df = pl.DataFrame({"ratio": [2.0, 2.0], "n": [1, 3]}).select(
+            ratio=plqt.pow(plqt.with_unit("ratio", "dimensionless"), "n")
+        )
+        assert df["ratio"].struct.field("value").to_list() == [2.0, 8.0]
+
+    def test_column_exponent_not_dimensionless(self):
+        df = pl.DataFrame({"length": [2.0], "n": [2]})
+        with pytest.raises(pl.exceptions.ComputeError, match="dimensionless base"):
+            df.select(plqt.pow(plqt.with_unit("length", "m"), "n"))
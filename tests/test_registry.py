@@ -0,0 +1,101 @@
+import subprocess
+import sys
+import textwrap
+
+import numpy as np
+import polars as pl
+import pytest
+
+import polars_qt as plqt
+
+
+def convert(values, unit, to):
+    df = pl.DataFrame({"a": values}).select(
+        a=plqt.with_unit("a", unit).qt.convert(to)
+    )
+    return df["a"].struct.field("value").to_numpy()
+
+
+def test_define_unit():
+    plqt.define_dimension("currency")
+    plqt.define_unit("euro", dimension="currency", aliases=["EUR"])
+    plqt.define_unit("cent", factor=0.01, base_unit="euro", aliases=["ct"])
+    assert np.allclose(convert([150.0, 20.0], "ct/m", "EUR/km"), [1500.0, 200.0])
+
+
+def test_redefine_dimension():
+    with pytest.raises(ValueError, match="already defined"):
+        plqt.define_dimension("length")
+    plqt.define_dimension("redefined_test_dimension", "[length] / [time]")
+    plqt.define_dimension(
+        "redefined_test_dimension", "[length] / [time] ** 2", replace=True
+    )
+
+
+def test_define_unit_dimension_mismatch():
+    with pytest.raises(ValueError, match="dimension"):
+        plqt.define_unit(
+            "not_a_furlong", dimension="time", factor=201.168, base_unit="meter"
+        )
+
+
+def test_define_existing_unit():
+    with pytest.raises(ValueError, match="already defined"):
+        plqt.define_unit("meter", base_unit="foot")
+    # aliases and prefixed units are existing units too
+    with pytest.raises(ValueError, match="already defined"):
+        plqt.define_unit("m", base_unit="foot")
+    with pytest.raises(ValueError, match="already defined"):
+        plqt.define_unit("kilometer", base_unit="foot")
+    assert np.allclose(convert([1.0], "m", "cm"), [100.0])
+
+    plqt.define_unit("replaced_test_unit", factor=2.0, base_unit="meter")
+    plqt.define_unit("replaced_test_unit", factor=3.0, base_unit="meter", replace=True)
+    assert np.allclose(convert([1.0], "replaced_test_unit", "m"), [3.0])
+
+
+def test_load_definitions():
+    plqt.load_definitions(
+        """
+        smoot = 67 * inch = Sm
+        """
+    )
+    assert np.allclose(convert([364.4], "Sm", "m"), [364.4 * 67 * 0.0254])
+
+
+def test_load_definitions_existing_unit():
+    with pytest.raises(ValueError, match="unit m is already defined"):
+        plqt.load_definitions("shadowing_test_unit = 2 * meter = m")
+    assert np.allclose(convert([1.0], "m", "cm"), [100.0])
+
+    plqt.load_definitions("replaced_loaded_unit = 2 * meter")
+    plqt.load_definitions("replaced_loaded_unit = 3 * meter", replace=True)
+    assert np.allclose(convert([1.0], "replaced_loaded_unit", "m"), [3.0])
+
+
+def test_load_definitions_is_atomic():
+    with pytest.raises(ValueError, match="line 3"):
+        plqt.load_definitions("atomic_test_unit = 2 * meter\n\nbroken = 3 * not_a_unit")
+    with pytest.raises(pl.exceptions.ComputeError):
+        convert([1.0], "m", "atomic_test_unit")
+
+
+def test_load_definitions_from_scratch():
+    # in a new process, as removing the built-in units would break the other tests
+    code = textwrap.dedent(
+        """
+        import polars as pl
+        import pytest
+
+        import polars_qt as plqt
+
+        plqt.load_definitions(
+            "meter = [length] = m\\nfoot = 0.3048 * meter = ft", from_scratch=True
+        )
+        df = pl.DataFrame({"a": [1.0]}).select(plqt.with_unit("a", "ft").qt.convert("m"))
+        assert df["a"].struct.field("value").to_list() == [0.3048]
+        with pytest.raises(pl.exceptions.PolarsError):
+            pl.DataFrame({"a": [1.0]}).select(plqt.with_unit("a", "s").qt.convert("m"))
+        """
+    )
+    subprocess.run([sys.executable, "-c", code], check=True)
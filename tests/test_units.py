@@ -14,13 +14,23 @@ class TestQtUnits:
         unit = QtUnit(name="meter", power=Fraction(3, 4))
         lit_expr = QtUnits([unit]).as_lit()
         series_from_lit = pl.DataFrame().select(a=lit_expr)["a"]
-        expected_series = pl.Series(
-            "a",
-            [[{"name": "meter", "power": {"numer": 3, "denom": 4}}]],
-            dtype=UnitDType,
-        )
+        expected_series = pl.Series("a", ["meter^(3/4)"], dtype=UnitDType)
         assert (series_from_lit == expected_series).all()
 
+    @pytest.mark.parametrize(
+        "unit", ["kg*m/s^2", "s^(1/2)", "m/(kg*s^(3/2))", "1/s", "dimensionless"]
+    )
+    def test_str_round_trip(self, unit):
+        assert str(QtUnits.from_str(unit)) == unit
+
+    def test_from_quantity_scalar(self):
+        list_unit = [
+            {"name": "m", "power": {"numer": 1, "denom": 1}},
+            {"name": "s", "power": {"numer": -2, "denom": 1}},
+        ]
+        units = QtUnits.from_quantity_scalar({"value": 1.0, "unit": "m/s^2"})
+        assert units == QtUnits.from_quantity_scalar({"value": 1.0, "unit": list_unit})
+
     def test_qtunits_initialization(self):
         unit1 = QtUnit(name="meter", power=Fraction(1, 2))
         unit2 = QtUnit(name="second", power=Fraction(3, 4))